@@ -0,0 +1,34 @@
+//! Fill throughput for small elements with the default cache line sized tasks against single element tasks,
+//! across pool sizes, so regressions in the chunking heuristic show up as a shrinking gap.
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use par_array_init::{par_array_init_chunked, par_array_init_in};
+
+const LEN: usize = 1 << 16;
+
+macro_rules! bench_chunking {
+    ($c:expr, $($elem:ty),*) => {$({
+        let mut group = $c.benchmark_group(concat!("chunking_", stringify!($elem)));
+        group.throughput(Throughput::Bytes((LEN * std::mem::size_of::<$elem>()) as u64));
+        for threads in [2, 4, 8] {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            let work = |i: usize| (i as $elem).wrapping_mul(31);
+            group.bench_with_input(BenchmarkId::new("cache_line", threads), &pool, |b, pool| {
+                b.iter(|| par_array_init_in::<Box<[$elem; LEN]>, _>(pool, work))
+            });
+            group.bench_with_input(BenchmarkId::new("per_element", threads), &pool, |b, pool| {
+                b.iter(|| pool.install(|| Box::new(par_array_init_chunked::<[$elem; LEN], _>(1, work))))
+            });
+        }
+        group.finish();
+    })*};
+}
+
+fn chunking(c: &mut Criterion) {
+    bench_chunking!(c, u8, u16);
+}
+
+criterion_group!(benches, chunking);
+criterion_main!(benches);
@@ -0,0 +1,27 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use par_array_init::par_array_init;
+
+const LEN: usize = 1 << 14;
+
+/// Same layout as `u64`, but with a `Drop` impl so the fill has to keep its drop guard.
+struct Moved(u64);
+
+impl Drop for Moved {
+    fn drop(&mut self) {
+        criterion::black_box(self.0);
+    }
+}
+
+fn copy_vs_move(c: &mut Criterion) {
+    let mut group = c.benchmark_group("copy_vs_move");
+    group.bench_function("copy", |b| {
+        b.iter(|| Box::new(par_array_init::<[u64; LEN], _>(|i| i as u64)))
+    });
+    group.bench_function("move", |b| {
+        b.iter(|| Box::new(par_array_init::<[Moved; LEN], _>(|i| Moved(i as u64))))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, copy_vs_move);
+criterion_main!(benches);
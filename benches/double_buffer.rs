@@ -0,0 +1,35 @@
+//! Compares the default fill against `par_array_init_double_buffered` for elements that are costly to write out,
+//! where overlapping moving one batch into place with computing the next should pay off if it ever does.
+use criterion::{criterion_group, criterion_main, Criterion};
+use par_array_init::{par_array_init, par_array_init_double_buffered};
+
+const LEN: usize = 2048;
+
+/// 512 bytes per element, so writing each one out is a real cost next to computing it.
+type Wide = [u64; 64];
+
+fn work(i: usize) -> Wide {
+    let mut elem = [0; 64];
+    let mut acc = i as u64;
+    for word in elem.iter_mut() {
+        acc = acc
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        *word = acc;
+    }
+    elem
+}
+
+fn double_buffer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("double_buffer");
+    group.bench_function("direct", |b| {
+        b.iter(|| par_array_init::<Box<[Wide; LEN]>, _>(work))
+    });
+    group.bench_function("double_buffered", |b| {
+        b.iter(|| Box::new(par_array_init_double_buffered::<[Wide; LEN], _>(work)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, double_buffer);
+criterion_main!(benches);
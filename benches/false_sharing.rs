@@ -0,0 +1,24 @@
+//! Compares the default fill, which gives each task at least a cache line of elements, against splitting a
+//! `[u8; 65536]` all the way down to single elements, where neighbouring bytes can be written by different threads.
+use criterion::{criterion_group, criterion_main, Criterion};
+use par_array_init::{par_array_init, par_array_init_chunked};
+
+const LEN: usize = 1 << 16;
+
+fn work(i: usize) -> u8 {
+    (i as u8).wrapping_mul(31)
+}
+
+fn false_sharing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("false_sharing");
+    group.bench_function("cache_line_chunks", |b| {
+        b.iter(|| par_array_init::<Box<[u8; LEN]>, _>(work))
+    });
+    group.bench_function("per_element_chunks", |b| {
+        b.iter(|| Box::new(par_array_init_chunked::<[u8; LEN], _>(1, work)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, false_sharing);
+criterion_main!(benches);
@@ -0,0 +1,40 @@
+//! Compares `par_array_init::from_fn` against `std::array::from_fn` to find where parallelism starts to pay off.
+//!
+//! Each group benches both functions at several lengths, with ids `par/<len>` and `std/<len>`. The crossover is the
+//! smallest length where `par` beats `std`, expect it to be much smaller for the expensive closure than the trivial
+//! one, and to move with the number of threads (with a single thread `std` always wins).
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+fn trivial(i: usize) -> u64 {
+    (i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+
+fn expensive(i: usize) -> u64 {
+    (0..1024).fold(i as u64, |acc, x| acc.wrapping_mul(31).wrapping_add(x))
+}
+
+macro_rules! bench_lengths {
+    ($group:expr, $work:expr, $($len:expr),*) => {
+        $(
+            $group.bench_function(BenchmarkId::new("par", $len), |b| {
+                b.iter(|| par_array_init::from_fn::<u64, $len, _>($work))
+            });
+            $group.bench_function(BenchmarkId::new("std", $len), |b| {
+                b.iter(|| std::array::from_fn::<u64, $len, _>($work))
+            });
+        )*
+    };
+}
+
+fn from_fn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("from_fn_trivial");
+    bench_lengths!(group, trivial, 16, 256, 4096, 16384);
+    group.finish();
+
+    let mut group = c.benchmark_group("from_fn_expensive");
+    bench_lengths!(group, expensive, 16, 256, 4096);
+    group.finish();
+}
+
+criterion_group!(benches, from_fn);
+criterion_main!(benches);
@@ -0,0 +1,56 @@
+//! Compares the two ways of pairing a parallel iterator's elements with their slots:
+//! zipping an index range with the source, which is what `from_par_iter` does, against enumerating the source.
+//! Both write through a raw pointer the same way, so the only difference is the shape of the iterator.
+use criterion::{criterion_group, criterion_main, Criterion};
+use par_array_init::from_par_iter;
+use rayon::prelude::*;
+use std::mem::MaybeUninit;
+
+const LEN: usize = 1 << 16;
+
+/// Base of the slots being filled, shared across the pool.
+#[derive(Clone, Copy)]
+struct Slots(*mut u64);
+
+unsafe impl Send for Slots {}
+unsafe impl Sync for Slots {}
+
+impl Slots {
+    fn write(self, i: usize, elem: u64) {
+        unsafe { self.0.add(i).write(elem) }
+    }
+}
+
+fn fill_zip<I: IndexedParallelIterator<Item = u64>>(slots: &mut [MaybeUninit<u64>], iter: I) {
+    let base = Slots(slots.as_mut_ptr() as *mut u64);
+    (0..slots.len())
+        .into_par_iter()
+        .zip(iter)
+        .for_each(|(i, elem)| base.write(i, elem));
+}
+
+fn fill_enumerate<I: IndexedParallelIterator<Item = u64>>(slots: &mut [MaybeUninit<u64>], iter: I) {
+    let base = Slots(slots.as_mut_ptr() as *mut u64);
+    iter.take(slots.len())
+        .enumerate()
+        .for_each(|(i, elem)| base.write(i, elem));
+}
+
+fn from_iter(c: &mut Criterion) {
+    let source: Vec<u64> = (0..LEN as u64).collect();
+    let mut slots: Box<[MaybeUninit<u64>]> = Box::new_uninit_slice(LEN);
+    let mut group = c.benchmark_group("from_iter");
+    group.bench_function("zip", |b| {
+        b.iter(|| fill_zip(&mut slots, source.par_iter().copied()))
+    });
+    group.bench_function("enumerate", |b| {
+        b.iter(|| fill_enumerate(&mut slots, source.par_iter().copied()))
+    });
+    group.bench_function("from_par_iter", |b| {
+        b.iter(|| Box::new(from_par_iter::<[u64; LEN], _>(source.par_iter().copied()).unwrap()))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, from_iter);
+criterion_main!(benches);
@@ -0,0 +1,25 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use par_array_init::{from_par_iter, par_array_init};
+use rayon::prelude::*;
+
+const LEN: usize = 1 << 16;
+
+fn work(i: usize) -> u64 {
+    (i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+
+fn range_fill(c: &mut Criterion) {
+    let mut group = c.benchmark_group("range_fill");
+    group.bench_function("par_array_init", |b| {
+        b.iter(|| par_array_init::<Box<[u64; LEN]>, _>(work))
+    });
+    group.bench_function("from_par_iter", |b| {
+        b.iter(|| {
+            Box::new(from_par_iter::<[u64; LEN], _>((0..LEN).into_par_iter().map(work)).unwrap())
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, range_fill);
+criterion_main!(benches);
@@ -0,0 +1,25 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use par_array_init::par_array_init_in;
+
+const LEN: usize = 1 << 16;
+
+fn work(i: usize) -> u64 {
+    (0..64).fold(i as u64, |acc, x| acc.wrapping_mul(31).wrapping_add(x))
+}
+
+fn thread_pool_sizes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("thread_pool_size");
+    for threads in [1, 2, 4, 8] {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(threads), &pool, |b, pool| {
+            b.iter(|| Box::new(par_array_init_in::<[u64; LEN], _>(pool, work)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, thread_pool_sizes);
+criterion_main!(benches);
@@ -28,163 +28,7594 @@
 //! let arr: Option<[u32; 50]> = par_array_init::from_par_iter(iter);
 //! ```
 //!
-extern crate array_init;
+//! # WebAssembly
+//! On `wasm32` targets without the `atomics` target feature there are no threads to run on, so functions filling from an index (like `par_array_init`) are compiled to a plain sequential loop instead of going through rayon. The api is the same, code using it builds for `wasm32-unknown-unknown` unchanged.
+//! Fills from a parallel iterator and over an existing array are driven in order on the current thread the same way. Functions that build their own thread pool, like `par_array_init_deterministic_pool`, return an error.
+//!
+//! # Deterministic mode
+//! The `deterministic` feature compiles the same sequential loop in on every target, so functions filling from an index call their closure in ascending index order on the calling thread. The arrays they build are the same either way, only the order and thread of the calls change, which makes downstream test suites reproducible.
+//! The same goes for every other function in the crate: fills from a parallel iterator walk it in order on the calling thread instead of splitting it, the functions over an existing array (like `par_overwrite_with` or `par_array_init_sorted`) visit its elements in order, and functions that would spawn or broadcast to the pool (like `par_array_init_pinned` or `par_array_init_channel`) do their work on the calling thread instead. Fallible fills stop at the first error. The only exception is an explicit pool, like `par_array_init_in`, where the fill still runs on the pool but on one of its threads, in order.
+//!
+//! # Metrics
+//! The `metrics` feature reports every fill through the `metrics` facade to whichever recorder is installed: the `par_array_init.calls` and `par_array_init.elements` counters, and a `par_array_init.duration` histogram in seconds. Without the feature nothing is recorded or measured.
+//! A fill is one call to a public function that fills an array, a slice or a buffer, whatever its length. It's counted once even if it's built out of several passes, like `par_array_init_symmetric` or `par_array_init_double_buffered`, and not at all if it returns without writing anything, like `from_par_iter` on a short iterator.
+//!
+#[cfg(feature = "arrayvec")]
+extern crate arrayvec;
+#[cfg(feature = "serde")]
+extern crate bincode;
+#[cfg(feature = "bitvec")]
+extern crate bitvec;
+#[cfg(feature = "bytemuck")]
+extern crate bytemuck;
+#[cfg(feature = "async")]
+extern crate futures;
+#[cfg(feature = "metrics")]
+extern crate metrics;
+#[cfg(all(feature = "perf", target_os = "linux"))]
+extern crate perf_event;
+#[cfg(feature = "rand")]
+extern crate rand;
 extern crate rayon;
+extern crate rayon_core;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "tracing")]
+extern crate tracing;
 
-use array_init::IsArray;
-use rayon::prelude::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+use rayon::iter::plumbing::{Consumer, Producer, ProducerCallback, UnindexedConsumer};
+use rayon::prelude::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator,
+    IntoParallelRefMutIterator, ParallelIterator,
+};
+use std::mem::MaybeUninit;
 
 /// Initialize an array given a function from index to element
-pub fn par_array_init<Array, F>(initializer: F) -> Array
+///
+/// The output can be any `FromParallelArray`, so `[T; N]`, `Box<[T; N]>` and `Arc<[T; N]>` can all be built directly.
+#[must_use]
+pub fn par_array_init<Out, F>(initializer: F) -> Out
+where
+    Out: FromParallelArray,
+    F: Send + Sync + Fn(usize) -> Out::Item,
+    Out::Item: Send,
+{
+    Out::from_par_fn(initializer)
+}
+
+/// Initialize an array given a function from index to any value that converts into an element.
+///
+/// Each value is converted with `Into` in parallel, right after it's produced, so the closure can skip boilerplate conversions like `String::from`.
+#[must_use]
+pub fn par_array_init_into<Out, F, V>(initializer: F) -> Out
+where
+    Out: FromParallelArray,
+    F: Send + Sync + Fn(usize) -> V,
+    V: Into<Out::Item>,
+    Out::Item: Send,
+{
+    par_array_init(|i| initializer(i).into())
+}
+
+/// Initialize an array given a function from index to element, running the work on `pool` instead of the global thread pool.
+#[must_use]
+pub fn par_array_init_in<Out, F>(pool: &rayon::ThreadPool, initializer: F) -> Out
+where
+    Out: FromParallelArray + Send,
+    F: Send + Sync + Fn(usize) -> Out::Item,
+    Out::Item: Send,
+{
+    pool.install(|| par_array_init(initializer))
+}
+
+/// Initialize an array given a function from index to element, spawning its chunks as tasks of a `rayon::in_place_scope`.
+///
+/// For fills that are part of a larger computation already running in a `rayon::scope`. Chunks of the array are spawned as tasks rather than split by a parallel iterator, so the pool's threads pick them up alongside the tasks of the scope it's called from.
+/// The array lives on the caller's stack, so the chunks can't be spawned onto an outer scope that might outlive this call. The nested scope is entered on the current thread, and this returns once every chunk has been written.
+#[must_use]
+pub fn par_array_init_in_scope<Array, F>(initializer: F) -> Array
 where
-    Array: Send + Sync + IsParArray,
+    Array: IsParArray,
     F: Send + Sync + Fn(usize) -> Array::Item,
-    Array::Item: Send + Sync + Copy,
+    Array::Item: Send,
 {
-    let elems = (0..Array::len()).into_par_iter().map(|i| initializer(i));
-    // Since our iterator is 0..len here we're safe to unwrap
-    from_par_iter(elems).unwrap()
+    let len = Array::len();
+    let chunk_len = default_min_len::<Array::Item>(len);
+    let span = FillSpan::new(len);
+    let mut ret = MaybeUninit::<Array>::uninit();
+    if SEQUENTIAL {
+        fill_in_order(uninit_slots(&mut ret), initializer, &span);
+        return unsafe { ret.assume_init() };
+    }
+    let origin = Written::new(uninit_slots(&mut ret).as_mut_ptr() as *mut Array::Item);
+    // Finished chunks are kept here until the end, if any chunk panics they're dropped as this unwinds
+    let finished = std::sync::Mutex::new(Vec::new());
+    rayon::in_place_scope(|s| {
+        for start in (0..len).step_by(chunk_len) {
+            let (origin, finished, initializer, span) = (&origin, &finished, &initializer, &span);
+            s.spawn(move |_| {
+                let mut written = origin.empty();
+                // Saturate so the last chunk of a huge zero sized array can't wrap around
+                for i in start..start.saturating_add(chunk_len).min(len) {
+                    let _permit = Permit::acquire();
+                    written.push(i, initializer(i));
+                }
+                span.chunk(written.start, written.len);
+                finished
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner)
+                    .push(written);
+            });
+        }
+    });
+    // Every chunk was spawned and the scope waited on all of them, so every slot was written
+    let finished = finished
+        .into_inner()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    finished.into_iter().for_each(std::mem::forget);
+    unsafe { ret.assume_init() }
 }
 
-/// Initialize an array given a parallel iterator.
+/// Initialize an array given a function from index to element, running the work on a freshly built pool with exactly `threads` threads.
 ///
-/// IndexedParallelIterator is required (as opposed to just ParallelIterator) so we know we have atleast as many elements as array length.
+/// The fill is isolated from whatever else is using the global pool, which makes timings more reproducible when benchmarking or pinning performance. Building the pool costs more than the fill for small arrays, reuse a pool with `par_array_init_in` if you're filling repeatedly.
+/// Returns an error if the pool can't be built.
+pub fn par_array_init_deterministic_pool<Out, F>(
+    threads: usize,
+    initializer: F,
+) -> Result<Out, rayon::ThreadPoolBuildError>
+where
+    Out: FromParallelArray + Send,
+    F: Send + Sync + Fn(usize) -> Out::Item,
+    Out::Item: Send,
+{
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()?;
+    Ok(par_array_init_in(&pool, initializer))
+}
+
+/// Initialize an array given a function from index to element, on a pool sized by the `PAR_ARRAY_INIT_THREADS` environment variable.
 ///
-/// Takes elements from the iterator until the Array is full, returns full array on completion. Returns None if there are not enough elements.
-pub fn from_par_iter<Array, I>(into_iter: I) -> Option<Array>
+/// When `PAR_ARRAY_INIT_THREADS` is set to a positive number a dedicated pool with that many threads is built for the fill, so deployments can tune it without code changes. When it's unset, isn't a positive number, or the pool can't be built, the fill runs on the current pool like `par_array_init`.
+#[must_use]
+pub fn par_array_init_env<Out, F>(initializer: F) -> Out
+where
+    Out: FromParallelArray + Send,
+    F: Send + Sync + Fn(usize) -> Out::Item,
+    Out::Item: Send,
+{
+    let threads = std::env::var(THREADS_ENV_VAR)
+        .ok()
+        .and_then(|threads| threads.trim().parse::<usize>().ok())
+        .filter(|&threads| threads > 0);
+    match threads.map(|threads| par_array_init_deterministic_pool(threads, &initializer)) {
+        Some(Ok(out)) => out,
+        _ => par_array_init(initializer),
+    }
+}
+
+/// Environment variable `par_array_init_env` reads its thread count from.
+pub const THREADS_ENV_VAR: &str = "PAR_ARRAY_INIT_THREADS";
+
+/// Limit how many elements can be computed at once across every fill in the process, or remove the limit by passing 0.
+///
+/// Each call to an initializer from a fill driven by a function of the index, like `par_array_init`, `par_overwrite` or `try_par_array_init`, holds a permit while it runs and waits for one if `limit` are already taken. That keeps concurrent fills whose closures spawn work of their own from oversubscribing the machine.
+/// The exceptions are `par_array_init_yielding`, whose calls could otherwise wait on a permit held by the call that yielded to them, and fills whose elements come out of a parallel iterator or an existing array, like `from_par_iter`, `par_array_init_iter` or `par_array_map`, where the elements are computed by the iterator rather than by a call the fill makes.
+/// Closures that start a fill of their own while holding a permit can deadlock if the limit is too low to cover both levels.
+pub fn set_global_concurrency_limit(limit: usize) {
+    let _in_use = PERMITS.lock().unwrap_or_else(|poison| poison.into_inner());
+    CONCURRENCY_LIMIT.store(limit, std::sync::atomic::Ordering::Relaxed);
+    // A raised limit may free up permits for waiting tasks
+    PERMIT_FREED.notify_all();
+}
+
+/// Initialize an array given a function from index to element, checking that the result doesn't depend on how the work was scheduled.
+///
+/// Requires the `debug-determinism` feature, and is meant for tests. The array is filled twice, on a single thread pool and on a pool with several threads, and this panics if the two arrays differ. That catches closures that depend on the order elements are initialized in, like ones reading a shared counter.
+#[cfg(feature = "debug-determinism")]
+#[must_use]
+pub fn par_array_init_check_determinism<Array, F>(initializer: F) -> Array
+where
+    Array: IsParArray + Send,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send + PartialEq + std::fmt::Debug,
+{
+    let span = FillSpan::new(Array::len());
+    let fill = |threads| {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap_or_else(|error| panic!("par_array_init: couldn't build a pool: {}", error));
+        pool.install(|| {
+            let mut ret = MaybeUninit::<Array>::uninit();
+            init_in_place(&mut ret, &initializer, &span);
+            unsafe { ret.assume_init() }
+        })
+    };
+    let mut sequential = fill(1);
+    let mut parallel = fill(rayon::current_num_threads().max(4));
+    let mismatch = {
+        let (sequential, parallel) = (sequential.mut_slice(), parallel.mut_slice());
+        (0..Array::len())
+            .find(|&i| sequential[i] != parallel[i])
+            .map(|i| {
+                format!(
+                    "index {} was {:?} on one thread and {:?} on several",
+                    i, sequential[i], parallel[i]
+                )
+            })
+    };
+    if let Some(mismatch) = mismatch {
+        panic!(
+            "par_array_init: initializer is not deterministic, {}",
+            mismatch
+        );
+    }
+    parallel
+}
+
+/// Initialize an array given a function from index to element, aborting the process instead of unwinding if `initializer` panics.
+///
+/// This is an FFI safety measure for fills running under an `extern "C"` function, where unwinding across the boundary is undefined behavior.
+/// Elements written before the panic are dropped first, then the process is aborted with `std::process::abort`.
+#[must_use]
+pub fn par_array_init_abort_on_panic<Out, F>(initializer: F) -> Out
+where
+    Out: FromParallelArray,
+    F: Send + Sync + Fn(usize) -> Out::Item,
+    Out::Item: Send,
+{
+    // catch_unwind only returns once the fill has unwound, so the written elements are already dropped
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| par_array_init(initializer))) {
+        Ok(out) => out,
+        Err(_) => std::process::abort(),
+    }
+}
+
+/// Initialize an array given a function from index to element, filling it sequentially instead when the pool looks busy.
+///
+/// Under heavy load, queuing more parallel work can add latency for everything on the pool. This checks two signals before splitting the fill: whether the current worker thread already has tasks waiting in its queue, and whether there are already as many `par_array_init_non_blocking` fills in flight as the pool has threads. If either says the pool is busy, the array is filled on the calling thread.
+/// Rayon doesn't expose how many of its threads are idle, so this is only a heuristic. It can't see work queued on other threads, or work submitted other ways, and may parallelize on a busy pool or fill sequentially on a quiet one.
+#[must_use]
+pub fn par_array_init_non_blocking<Array, F>(initializer: F) -> Array
 where
-    I: IntoParallelIterator<Item = Array::Item>,
-    I::Iter: IndexedParallelIterator<Item = Array::Item>,
     Array: IsParArray,
-    Array::Item: Copy + Send + Sync,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send,
 {
-    let iter = into_iter.into_par_iter();
-    if Array::len() > iter.len() {
-        return None;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+    struct InFlight;
+    impl Drop for InFlight {
+        fn drop(&mut self) {
+            IN_FLIGHT.fetch_sub(1, Ordering::Relaxed);
+        }
     }
-    let mut ret: Array = unsafe { std::mem::uninitialized() };
-    ret.mut_slice()
-        .into_par_iter()
-        .zip(iter)
-        .for_each(|(dst, src)| {
-            *dst = src;
+
+    let _in_flight = InFlight;
+    let others = IN_FLIGHT.fetch_add(1, Ordering::Relaxed);
+    let busy = rayon_core::current_thread_has_pending_tasks().unwrap_or(false)
+        || others >= rayon::current_num_threads();
+    let mut ret = MaybeUninit::<Array>::uninit();
+    if busy {
+        fill_in_order(
+            uninit_slots(&mut ret),
+            initializer,
+            &FillSpan::with_threads(Array::len(), 1),
+        );
+    } else {
+        init_in_place(&mut ret, initializer, &FillSpan::new(Array::len()));
+    }
+    unsafe { ret.assume_init() }
+}
+
+/// Types that can be built from an array initialized in parallel.
+///
+/// This lets `par_array_init` fill arrays wherever they're going to live, boxed and `Arc`ed arrays are written in place on the heap rather than built on the stack and moved.
+pub trait FromParallelArray: Sized {
+    /// Type of the array's elements.
+    type Item;
+    /// Build `Self` given a function from index to element, called in parallel.
+    #[must_use]
+    fn from_par_fn<F>(initializer: F) -> Self
+    where
+        F: Send + Sync + Fn(usize) -> Self::Item,
+        Self::Item: Send;
+}
+
+impl<T, const N: usize> FromParallelArray for [T; N] {
+    type Item = T;
+
+    fn from_par_fn<F>(initializer: F) -> Self
+    where
+        F: Send + Sync + Fn(usize) -> T,
+        T: Send,
+    {
+        let mut ret = MaybeUninit::uninit();
+        init_in_place(&mut ret, initializer, &FillSpan::new(N));
+        unsafe { ret.assume_init() }
+    }
+}
+
+impl<T, const N: usize> FromParallelArray for Box<[T; N]> {
+    type Item = T;
+
+    fn from_par_fn<F>(initializer: F) -> Self
+    where
+        F: Send + Sync + Fn(usize) -> T,
+        T: Send,
+    {
+        // Filled in place on the heap, so the array never passes through the stack
+        let mut ret = Box::new_uninit();
+        init_in_place(&mut ret, initializer, &FillSpan::new(N));
+        unsafe { ret.assume_init() }
+    }
+}
+
+impl<T, const N: usize> FromParallelArray for std::sync::Arc<[T; N]> {
+    type Item = T;
+
+    fn from_par_fn<F>(initializer: F) -> Self
+    where
+        F: Send + Sync + Fn(usize) -> T,
+        T: Send,
+    {
+        let mut ret = std::sync::Arc::new_uninit();
+        // We just created the Arc so no one else can have a reference to it
+        let slot = std::sync::Arc::get_mut(&mut ret).unwrap_or_else(|| {
+            unreachable!("par_array_init: a newly created Arc was already shared")
         });
-    Some(ret)
+        init_in_place(slot, initializer, &FillSpan::new(N));
+        unsafe { ret.assume_init() }
+    }
 }
 
-/// Extension of `array_init::IsArray` that includes method to convert the array to a mutable slice.
-/// `&mut [T]` implements parallel iterator so this allows us to write our elements in parallel without having to explicitly pass a `*mut T` across threads.
-pub trait IsParArray: IsArray {
-    /// Convert array to a mutable slice.
-    fn mut_slice(&mut self) -> &mut [Self::Item];
+/// Initialize every element of `array` given a function from index to element.
+fn init_in_place<Array, F>(array: &mut MaybeUninit<Array>, initializer: F, span: &FillSpan)
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send,
+{
+    fill_par_fn(
+        uninit_slots(array),
+        default_min_len::<Array::Item>(Array::len()),
+        initializer,
+        span,
+    );
 }
 
-macro_rules! impl_is_par_array {
-    ($($size:expr)+) => ($(
-        impl<T> IsParArray for [T; $size] {
-            fn mut_slice(&mut self) -> &mut [Self::Item] {
-                self.as_mut()
-            }
-        }
-    )+);
-}
-
-// This is like straight up copied from (array-init's impl)[https://github.com/Manishearth/array-init/blob/master/src/lib.rs#L270]
-// let the legacy live on
-impl_is_par_array! {
-     0  1  2  3  4  5  6  7  8  9 10 11 12 13 14 15
-    16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31
-    32 33 34 35 36 37 38 39 40 41 42 43 44 45 46 47
-    48 49 50 51 52 53 54 55 56 57 58 59 60 61 62 63
-    64 65 66 67 68 69 70 71 72 73 74 75 76 77 78 79
-    80 81 82 83 84 85 86 87 88 89 90 91 92 93 94 95
-    96 97 98 99 100 101 102 103 104 105 106 107 108
-    109 110 111 112 113 114 115 116 117 118 119 120
-    121 122 123 124 125 126 127 128 129 130 131 132
-    133 134 135 136 137 138 139 140 141 142 143 144
-    145 146 147 148 149 150 151 152 153 154 155 156
-    157 158 159 160 161 162 163 164 165 166 167 168
-    169 170 171 172 173 174 175 176 177 178 179 180
-    181 182 183 184 185 186 187 188 189 190 191 192
-    193 194 195 196 197 198 199 200 201 202 203 204
-    205 206 207 208 209 210 211 212 213 214 215 216
-    217 218 219 220 221 222 223 224 225 226 227 228
-    229 230 231 232 233 234 235 236 237 238 239 240
-    241 242 243 244 245 246 247 248 249 250 251 252
-    253 254 255 256 257 258 259 260 261 262 263 264
-    265 266 267 268 269 270 271 272 273 274 275 276
-    277 278 279 280 281 282 283 284 285 286 287 288
-    289 290 291 292 293 294 295 296 297 298 299 300
-    301 302 303 304 305 306 307 308 309 310 311 312
-    313 314 315 316 317 318 319 320 321 322 323 324
-    325 326 327 328 329 330 331 332 333 334 335 336
-    337 338 339 340 341 342 343 344 345 346 347 348
-    349 350 351 352 353 354 355 356 357 358 359 360
-    361 362 363 364 365 366 367 368 369 370 371 372
-    373 374 375 376 377 378 379 380 381 382 383 384
-    385 386 387 388 389 390 391 392 393 394 395 396
-    397 398 399 400 401 402 403 404 405 406 407 408
-    409 410 411 412 413 414 415 416 417 418 419 420
-    421 422 423 424 425 426 427 428 429 430 431 432
-    433 434 435 436 437 438 439 440 441 442 443 444
-    445 446 447 448 449 450 451 452 453 454 455 456
-    457 458 459 460 461 462 463 464 465 466 467 468
-    469 470 471 472 473 474 475 476 477 478 479 480
-    481 482 483 484 485 486 487 488 489 490 491 492
-    493 494 495 496 497 498 499 500 501 502 503 504
-    505 506 507 508 509 510 511 512
+/// Number of tasks per thread the default fill splits into, the `K` in `default_min_len`.
+///
+/// Splitting into a few tasks per thread rather than one leaves room for work stealing to even out uneven closures, while keeping cheap closures from drowning in per-task overhead.
+pub const TASKS_PER_THREAD: usize = 4;
+
+/// Smallest number of elements the default fill gives to a single task, `max(len / (num_threads * TASKS_PER_THREAD), elements per cache line)`.
+///
+/// Tasks are never smaller than a cache line of elements, so small element types aren't written a few at a time by different threads sharing a cache line.
+fn default_min_len<T>(len: usize) -> usize {
+    let per_line = (CACHE_LINE_SIZE / size_of::<T>().max(1)).max(1);
+    (len / rayon::current_num_threads().saturating_mul(TASKS_PER_THREAD)).max(per_line)
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
+/// Cache line size in bytes assumed by the default fill when sizing tasks.
+///
+/// Use `par_array_init_chunked` to pick a different task size, e.g. `par_array_init_chunked(1, f)` to split all the way down to single elements.
+pub const CACHE_LINE_SIZE: usize = 64;
 
-    #[test]
-    fn test_array_init_in_parallel_works() {
-        let array: [usize; 10] = par_array_init(|i| i);
-        for (indx, i) in array.iter().enumerate() {
-            assert_eq!(*i, indx)
+/// Initialize an array given a function from index to element, taking its length from an existing slice.
+///
+/// For copying the shape of existing data, `template` is only used for its length. Panics if `template.len()` isn't `Array::len()`.
+#[must_use]
+pub fn par_array_init_like<Array, F>(template: &[Array::Item], initializer: F) -> Array
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send,
+{
+    assert_eq!(
+        template.len(),
+        Array::len(),
+        "par_array_init_like: template has a different length than the array"
+    );
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    init_in_place(&mut ret, initializer, &span);
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize an array given a function from an existing array and index to element, for pipelines where each stage is built from the one before.
+///
+/// `src` is shared read-only with every call, so it has to be `Sync`. Panics if the arrays have different lengths.
+#[must_use]
+pub fn par_array_init_from_array<A, B, F>(src: &A, initializer: F) -> B
+where
+    A: IsParArray + Sync,
+    B: IsParArray,
+    F: Send + Sync + Fn(&A, usize) -> B::Item,
+    B::Item: Send,
+{
+    assert_eq!(
+        A::len(),
+        B::len(),
+        "par_array_init_from_array: source has a different length than the array"
+    );
+    let span = FillSpan::new(B::len());
+    let mut ret = MaybeUninit::<B>::uninit();
+    init_in_place(&mut ret, |i| initializer(src, i), &span);
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize an array from two slices combined position by position, element `i` being `f(i, &a[i], &b[i])`.
+///
+/// Saves juggling indices across several inputs by hand. Panics if either slice's length isn't `Array::len()`.
+#[must_use]
+pub fn par_array_init_zip_slices<Array, A, B, F>(a: &[A], b: &[B], f: F) -> Array
+where
+    Array: IsParArray,
+    A: Sync,
+    B: Sync,
+    F: Send + Sync + Fn(usize, &A, &B) -> Array::Item,
+    Array::Item: Send,
+{
+    assert!(
+        a.len() == Array::len() && b.len() == Array::len(),
+        "par_array_init_zip_slices: slices of length {} and {} don't match the array length {}",
+        a.len(),
+        b.len(),
+        Array::len()
+    );
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    init_in_place(&mut ret, |i| f(i, &a[i], &b[i]), &span);
+    // Every slot was written
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize an owned array by cloning every element of `table` in parallel.
+///
+/// For lookup tables whose elements are expensive to clone, like a `const` table of `String`s, so the clones aren't made one after another.
+#[must_use]
+pub fn par_array_init_from_table<T, const N: usize>(table: &[T; N]) -> [T; N]
+where
+    T: Clone + Send + Sync,
+{
+    par_array_init(|i| table[i].clone())
+}
+
+/// Initialize an array by cloning `src` in parallel when it has the right length, or by calling `initializer` for each index otherwise.
+///
+/// Unifies reusing existing data with computing it from scratch, `initializer` is only called when `src` is None or isn't `Array::len()` long.
+#[must_use]
+pub fn par_array_from_slice_or_init<Array, F>(src: Option<&[Array::Item]>, initializer: F) -> Array
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Clone + Send + Sync,
+{
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    match src {
+        Some(src) if src.len() == Array::len() => {
+            init_in_place(&mut ret, |i| src[i].clone(), &span)
         }
+        _ => init_in_place(&mut ret, initializer, &span),
     }
+    unsafe { ret.assume_init() }
+}
 
-    #[test]
-    fn test_array_init_with_capturing_lambda_works() {
-        let vec = vec![40; 10];
-        let array: [usize; 10] = par_array_init(|i| vec[i]);
-        for i in array.iter() {
-            assert_eq!(*i, 40);
+/// Initialize an array given a function from index to element, also reporting which rayon thread filled each chunk.
+///
+/// For debugging data locality and work stealing. Each task's range of indices is paired with the `rayon::current_thread_index()` of the thread that ran it. The ranges are sorted and together cover the whole array.
+#[must_use]
+pub fn par_array_init_thread_map<Array, F>(
+    initializer: F,
+) -> (Array, Vec<(std::ops::Range<usize>, usize)>)
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send,
+{
+    let chunks = std::sync::Mutex::new(Vec::new());
+    let array = par_array_init_with_chunk_hook(initializer, |range| {
+        // Fills always run on a pool, so there's always a thread index
+        let thread = rayon::current_thread_index().unwrap_or(0);
+        chunks
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner())
+            .push((range, thread));
+    });
+    let mut chunks = chunks
+        .into_inner()
+        .unwrap_or_else(|poison| poison.into_inner());
+    chunks.sort_unstable_by_key(|(range, _)| range.start);
+    (array, chunks)
+}
+
+/// Initialize an array given a function from index to element, calling `on_chunk` with the range of indices each task filled.
+///
+/// `on_chunk` is called once per task rather than once per element, after the task has written its range, which shows how rayon actually split the fill. It has no effect on the array.
+#[must_use]
+pub fn par_array_init_with_chunk_hook<Array, F, H>(initializer: F, on_chunk: H) -> Array
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    H: Send + Sync + Fn(std::ops::Range<usize>),
+    Array::Item: Send,
+{
+    let len = Array::len();
+    let span = FillSpan::new(len);
+    let mut ret = MaybeUninit::<Array>::uninit();
+    if SEQUENTIAL {
+        fill_in_order(uninit_slots(&mut ret), initializer, &span);
+        if len > 0 {
+            on_chunk(0..len);
         }
+        return unsafe { ret.assume_init() };
     }
+    let origin = Written::new(uninit_slots(&mut ret).as_mut_ptr() as *mut Array::Item);
+    let written = (0..len)
+        .into_par_iter()
+        .with_min_len(default_min_len::<Array::Item>(len))
+        .fold(
+            || origin.empty(),
+            |mut written, i| {
+                let _permit = Permit::acquire();
+                written.push(i, initializer(i));
+                written
+            },
+        )
+        .inspect(|written| {
+            span.chunk(written.start, written.len);
+            if written.len > 0 {
+                on_chunk(written.start..written.start + written.len)
+            }
+        })
+        .reduce(|| origin.empty(), Written::merge);
+    // The index range covers every slot
+    std::mem::forget(written);
+    unsafe { ret.assume_init() }
+}
 
-    #[test]
-    fn test_from_iter_works() {
-        let vec = vec![1, 2, 3, 4];
-        let array: Option<[usize; 4]> = from_par_iter(vec.into_par_iter());
-        assert_eq!(array, Some([1, 2, 3, 4]));
+/// Initialize an array from a map of sparse overrides, calling `initializer` for every index that isn't overridden.
+///
+/// Overridden indices are cloned out of `overrides` in parallel with the rest being computed. `out_of_range` decides what happens to keys past the end of the array, with `OutOfRange::Reject` nothing is computed and the first such key found is returned as an error.
+pub fn par_array_init_sparse<Array, F, S>(
+    overrides: &std::collections::HashMap<usize, Array::Item, S>,
+    out_of_range: OutOfRange,
+    initializer: F,
+) -> Result<Array, OverrideError>
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Clone + Send + Sync,
+    S: std::hash::BuildHasher + Sync,
+{
+    let len = Array::len();
+    if out_of_range == OutOfRange::Reject {
+        if let Some(&index) = overrides.keys().find(|&&index| index >= len) {
+            return Err(OverrideError { index, len });
+        }
     }
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    init_in_place(
+        &mut ret,
+        |i| match overrides.get(&i) {
+            Some(elem) => elem.clone(),
+            None => initializer(i),
+        },
+        &span,
+    );
+    Ok(unsafe { ret.assume_init() })
+}
 
-    #[test]
-    fn test_from_iter_fails_when_len_is_different() {
-        let vec = vec![1, 2, 3, 4];
-        let array: Option<[usize; 10]> = from_par_iter(vec.into_par_iter());
-        assert_eq!(array, None);
+/// What `par_array_init_sparse` does with override keys past the end of the array.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutOfRange {
+    /// Skip them.
+    Ignore,
+    /// Fail with an `OverrideError`.
+    Reject,
+}
+
+/// Error returned by `par_array_init_sparse` when an override key is past the end of the array.
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OverrideError {
+    /// The out of range key.
+    pub index: usize,
+    /// Length of the array.
+    pub len: usize,
+}
+
+impl std::fmt::Display for OverrideError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "override at index {} is out of range for an array of length {}",
+            self.index, self.len
+        )
     }
+}
 
-    #[test]
-    fn test_initialization_works_with_larger_types() {
-        let vec = vec![
-            "A neat string",
-            "A lame string",
-            "A longish string abcdefghijklmnopqrstuvwxyz",
-        ];
-        let array: Option<[&str; 3]> = from_par_iter(vec.into_par_iter());
-        assert_eq!(
-            array,
-            Some([
-                "A neat string",
-                "A lame string",
-                "A longish string abcdefghijklmnopqrstuvwxyz"
-            ])
+impl std::error::Error for OverrideError {}
+
+/// Initialize an array given a function from index to element, giving up once the elements' sizes add up to more than `byte_budget`.
+///
+/// For elements that allocate a variable amount, `size(&elem)` reports how many bytes each one holds. Sizes are added up in the order elements finish, and as soon as one pushes the running total over the budget, indices after it are no longer started and every completed element is dropped.
+pub fn par_array_init_budgeted<Array, F, S>(
+    byte_budget: usize,
+    initializer: F,
+    size: S,
+) -> Result<Array, BudgetExceeded>
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    S: Send + Sync + Fn(&Array::Item) -> usize,
+    Array::Item: Send,
+{
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let used = AtomicUsize::new(0);
+    try_par_array_init(|i| {
+        let elem = initializer(i);
+        let bytes = size(&elem);
+        let before = used.fetch_add(bytes, Ordering::Relaxed);
+        if before.saturating_add(bytes) > byte_budget {
+            Err(BudgetExceeded {
+                index: i,
+                budget: byte_budget,
+            })
+        } else {
+            Ok(elem)
+        }
+    })
+}
+
+/// Error returned by `par_array_init_budgeted` when the elements need more than the budget.
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BudgetExceeded {
+    /// An index whose element took the running total over the budget.
+    ///
+    /// The total is added up in the order elements finish, so which index that is depends on scheduling and can differ between runs. It isn't necessarily the lowest index at which the sizes in index order go over the budget.
+    pub index: usize,
+    /// The budget in bytes.
+    pub budget: usize,
+}
+
+impl std::fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "element {} went over the budget of {} bytes",
+            self.index, self.budget
+        )
+    }
+}
+
+impl std::error::Error for BudgetExceeded {}
+
+/// Initialize an array given a function from index to element, then check `hash` of the whole array against `expected`.
+///
+/// For deterministic pipelines that know what their output should hash to. `hash` runs once over the finished array, and on a mismatch the array is dropped and the hash it actually had is returned in the error.
+pub fn par_array_init_checksummed<Array, F, H>(
+    initializer: F,
+    expected: u64,
+    hash: H,
+) -> Result<Array, ChecksumError>
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    H: FnOnce(&[Array::Item]) -> u64,
+    Array::Item: Send,
+{
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    init_in_place(&mut ret, initializer, &span);
+    // Every slot was written
+    let array = unsafe { ret.assume_init() };
+    let actual = hash(array.as_ffi_slice());
+    if actual == expected {
+        Ok(array)
+    } else {
+        Err(ChecksumError { expected, actual })
+    }
+}
+
+/// Error returned by `par_array_init_checksummed` when the array doesn't hash to the expected value.
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChecksumError {
+    /// Hash the array was expected to have.
+    pub expected: u64,
+    /// Hash the array actually had.
+    pub actual: u64,
+}
+
+impl std::fmt::Display for ChecksumError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "array hashed to {:#x} but {:#x} was expected",
+            self.actual, self.expected
+        )
+    }
+}
+
+impl std::error::Error for ChecksumError {}
+
+/// Initialize an array given a function from index to element, then check that the elements are non-decreasing.
+///
+/// For data that should come out sorted by construction. Once the array is filled, neighbouring elements are compared in parallel, and if any element isn't `<=` the one after it the array is dropped and the first such pair is reported. Elements that can't be compared, like a NaN, count as out of order.
+pub fn par_array_init_monotonic<Array, F>(initializer: F) -> Result<Array, MonotonicityError>
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send + Sync + PartialOrd,
+{
+    use rayon::slice::ParallelSlice;
+
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    init_in_place(&mut ret, initializer, &span);
+    // Every slot was written
+    let array = unsafe { ret.assume_init() };
+    let out_of_order = |pair: &[Array::Item]| {
+        !matches!(
+            pair[0].partial_cmp(&pair[1]),
+            Some(std::cmp::Ordering::Less | std::cmp::Ordering::Equal)
+        )
+    };
+    let elems = array.as_ffi_slice();
+    let decrease = if SEQUENTIAL {
+        elems.windows(2).position(out_of_order)
+    } else {
+        elems.par_windows(2).position_first(out_of_order)
+    };
+    match decrease {
+        None => Ok(array),
+        Some(index) => Err(MonotonicityError { index }),
+    }
+}
+
+/// Error returned by `par_array_init_monotonic` when an element is greater than the one after it.
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonotonicityError {
+    /// Lowest index whose element is out of order with the element at `index + 1`.
+    pub index: usize,
+}
+
+impl std::fmt::Display for MonotonicityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "element {} is out of order with element {}",
+            self.index,
+            self.index + 1
         )
     }
 }
+
+impl std::error::Error for MonotonicityError {}
+
+/// Initialize an array given a function from index to element, giving each task atleast `min_len` elements.
+///
+/// `par_array_init` picks a task size based on the array length and number of threads, this overrides it. Larger values mean fewer, longer running tasks.
+#[must_use]
+pub fn par_array_init_chunked<Array, F>(min_len: usize, initializer: F) -> Array
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send,
+{
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    fill_par_fn(uninit_slots(&mut ret), min_len.max(1), initializer, &span);
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize an array computing only the indices in `range` with `initializer`, and cloning `default` into every other slot.
+///
+/// For arrays that are mostly a default value. `initializer` is never called outside `range`.
+///
+/// # Panics
+///
+/// Panics if `range` goes past the end of the array.
+#[must_use]
+pub fn par_array_init_range<Array, F>(
+    range: std::ops::Range<usize>,
+    initializer: F,
+    default: Array::Item,
+) -> Array
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send + Sync + Clone,
+{
+    assert!(
+        range.end <= Array::len(),
+        "par_array_init_range: range end {} is past the array length {}",
+        range.end,
+        Array::len()
+    );
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    init_in_place(
+        &mut ret,
+        |i| {
+            if range.contains(&i) {
+                initializer(i)
+            } else {
+                default.clone()
+            }
+        },
+        &span,
+    );
+    // Every slot was written
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize an array given a function from index to element, overlapping computing one batch of elements with moving the previous batch into the array.
+///
+/// The array is filled in batches of about `num_threads` tasks' worth of elements. Each batch is computed in parallel into a scratch buffer while the batch before it is moved from its own scratch buffer into place, so for large elements the cost of writing them out can hide behind computing the next ones.
+/// Every element is moved twice, so for small or cheap to move elements `par_array_init` is faster. Measure with the `double_buffer` bench before reaching for this.
+#[must_use]
+pub fn par_array_init_double_buffered<Array, F>(initializer: F) -> Array
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send,
+{
+    let len = Array::len();
+    let batch = default_min_len::<Array::Item>(len)
+        .saturating_mul(rayon::current_num_threads())
+        .max(1);
+    // The batches are part of this fill's span
+    let compute = |start: usize| {
+        let batch_len = batch.min(len - start);
+        vec_init(batch_len, |i| initializer(start + i), &FillSpan::nested())
+    };
+    let span = FillSpan::new(len);
+    let mut ret = MaybeUninit::<Array>::uninit();
+    if SEQUENTIAL {
+        fill_in_order(uninit_slots(&mut ret), initializer, &span);
+        return unsafe { ret.assume_init() };
+    }
+    let slots = uninit_slots(&mut ret);
+    // Owns the batches that have been moved into place so far, dropping them if a later batch panics
+    let mut done = Written::new(slots.as_mut_ptr() as *mut Array::Item);
+    let mut computed = if len == 0 { Vec::new() } else { compute(0) };
+    for dst in slots.chunks_mut(batch) {
+        let next_start = done.len + dst.len();
+        let elems = std::mem::take(&mut computed);
+        // If computing the next batch panics, join drops this batch's run once it's been written
+        let (written, next) = rayon::join(
+            || {
+                let indexed = (0..dst.len()).into_par_iter().zip(elems);
+                write_indexed(dst, indexed, |elem| elem, &FillSpan::nested())
+            },
+            || (next_start < len).then(|| compute(next_start)),
+        );
+        std::mem::forget(written);
+        done.len = next_start;
+        computed = next.unwrap_or_default();
+    }
+    // Every batch was moved into place
+    std::mem::forget(done);
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize an array given a function from index to element, on exactly `threads` scoped OS threads instead of the rayon pool.
+///
+/// The indices are split into `threads` contiguous runs whose lengths differ by at most one, each filled from left to right by its own thread spawned with `std::thread::scope`. The call returns once every thread has been joined, and a panic on any of them is resumed on the caller after the others finish.
+/// Spawning threads costs far more than handing tasks to rayon, so this is for when the exact number of threads matters, not for speed.
+///
+/// # Panics
+/// Panics if `threads` is 0.
+#[must_use]
+pub fn par_array_init_scoped<Array, F>(threads: usize, initializer: F) -> Array
+where
+    Array: IsParArray,
+    F: Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send,
+{
+    assert!(threads > 0, "par_array_init_scoped: threads must not be 0");
+    let len = Array::len();
+    let span = FillSpan::with_threads(len, if SEQUENTIAL { 1 } else { threads });
+    let mut ret = MaybeUninit::<Array>::uninit();
+    if SEQUENTIAL {
+        fill_in_order(uninit_slots(&mut ret), initializer, &span);
+        return unsafe { ret.assume_init() };
+    }
+    let origin = Written::new(uninit_slots(&mut ret).as_mut_ptr() as *mut Array::Item);
+    let (initializer, span) = (&initializer, &span);
+    let written = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|t| {
+                let mut written = origin.empty();
+                scope.spawn(move || {
+                    for i in even_run(len, threads, t) {
+                        let _permit = Permit::acquire();
+                        written.push(i, initializer(i));
+                    }
+                    span.chunk(written.start, written.len);
+                    written
+                })
+            })
+            .collect();
+        // Handles that are never joined drop their thread's run when the scope joins them
+        handles
+            .into_iter()
+            .fold(origin.empty(), |acc, handle| match handle.join() {
+                Ok(run) => acc.merge(run),
+                Err(payload) => std::panic::resume_unwind(payload),
+            })
+    });
+    // The runs cover every slot
+    std::mem::forget(written);
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize an array given a function from index to element, giving every thread of the current pool exactly one contiguous run of indices.
+///
+/// The indices are split into one run per thread whose lengths differ by at most one, and each thread fills its own run from left to right through `rayon::broadcast`. Nothing is stolen, so how much work each thread does is fixed up front: this bounds per-thread latency for predictable workloads, at the cost of throughput when some elements are slower than others.
+/// A panic on any thread is resumed on the caller once every thread has finished.
+#[must_use]
+pub fn par_array_init_pinned<Array, F>(initializer: F) -> Array
+where
+    Array: IsParArray,
+    F: Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send,
+{
+    let len = Array::len();
+    let span = FillSpan::new(len);
+    let mut ret = MaybeUninit::<Array>::uninit();
+    if SEQUENTIAL {
+        fill_in_order(uninit_slots(&mut ret), initializer, &span);
+        return unsafe { ret.assume_init() };
+    }
+    let origin = Written::new(uninit_slots(&mut ret).as_mut_ptr() as *mut Array::Item);
+    let runs = rayon::broadcast(|ctx| {
+        let mut written = origin.empty();
+        for i in even_run(len, ctx.num_threads(), ctx.index()) {
+            let _permit = Permit::acquire();
+            written.push(i, initializer(i));
+        }
+        span.chunk(written.start, written.len);
+        written
+    });
+    // Broadcast results are in thread order, which is also the order of the runs
+    let written = runs.into_iter().fold(origin.empty(), Written::merge);
+    // The runs cover every slot
+    std::mem::forget(written);
+    unsafe { ret.assume_init() }
+}
+
+/// Indices of run `run` when `len` indices are split into `runs` contiguous runs whose lengths differ by at most one.
+fn even_run(len: usize, runs: usize, run: usize) -> std::ops::Range<usize> {
+    let (run_len, extra) = (len / runs, len % runs);
+    // The first `extra` runs get one more element so every index is covered
+    let start = |r: usize| r * run_len + r.min(extra);
+    start(run)..start(run + 1)
+}
+
+/// Initialize an array given a function from index and a yield hook to element, for long running calls that should let the worker get on with other tasks.
+///
+/// `initializer` can call `YieldNow::yield_now` between steps of its work, which runs `rayon::yield_now` so the thread can pick up other pending tasks, like other indices of this fill, before carrying on. That keeps a few very long calls from holding up shorter work queued behind them.
+/// Calls don't hold a permit under `set_global_concurrency_limit`, since a task run by a yield could wait forever for the permit held by the call that yielded.
+#[must_use]
+pub fn par_array_init_yielding<Array, F>(initializer: F) -> Array
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize, YieldNow) -> Array::Item,
+    Array::Item: Send,
+{
+    let len = Array::len();
+    let iter = (0..len)
+        .into_par_iter()
+        .with_min_len(default_min_len::<Array::Item>(len))
+        .map(|i| initializer(i, YieldNow { _private: () }));
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    fill_par_iter(uninit_slots(&mut ret), iter, &span);
+    unsafe { ret.assume_init() }
+}
+
+/// Hook passed to the closure of `par_array_init_yielding` for giving up the thread to other tasks.
+#[derive(Debug, Clone, Copy)]
+pub struct YieldNow {
+    _private: (),
+}
+
+impl YieldNow {
+    /// Run other pending tasks on this thread for a while, returning whether there were any.
+    pub fn yield_now(self) -> bool {
+        matches!(rayon::yield_now(), Some(rayon::Yield::Executed))
+    }
+}
+
+/// Initialize an array given a function from index to element, with chunk boundaries that don't depend on rayon's splitting.
+///
+/// The array is cut into chunks of `max(len / (num_threads * TASKS_PER_THREAD), elements per cache line)` elements, computed here rather than by rayon, so chunk `c` always covers `c * chunk_len..(c + 1) * chunk_len` (the last one may be shorter). Each chunk is one task calling `initializer` from left to right. Which thread runs a chunk and the order chunks run in are still up to rayon.
+/// Prefer `par_array_init` and closures that don't depend on call order, this is for code that can't avoid it.
+#[must_use]
+pub fn par_array_init_stable_order<Array, F>(initializer: F) -> Array
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send,
+{
+    let len = Array::len();
+    let chunk_len = default_min_len::<Array::Item>(len);
+    let span = FillSpan::new(len);
+    let mut ret = MaybeUninit::<Array>::uninit();
+    if SEQUENTIAL {
+        fill_in_order(uninit_slots(&mut ret), initializer, &span);
+        return unsafe { ret.assume_init() };
+    }
+    let origin = Written::new(uninit_slots(&mut ret).as_mut_ptr() as *mut Array::Item);
+    let written = (0..len.div_ceil(chunk_len))
+        .into_par_iter()
+        .map(|chunk| {
+            let start = chunk * chunk_len;
+            let mut written = origin.empty();
+            for i in start..len.min(start.saturating_add(chunk_len)) {
+                let _permit = Permit::acquire();
+                written.push(i, initializer(i));
+            }
+            span.chunk(written.start, written.len);
+            written
+        })
+        .reduce(|| origin.empty(), Written::merge);
+    // The chunks cover every slot
+    std::mem::forget(written);
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize a boxed slice whose length is only known at runtime by dispatching to a fixed size array fill, for a set of common lengths.
+///
+/// Expands to a `match` on `len` with one arm per supported length (0 and the powers of two from 1 to 4096), each filling a `Box<[T; L]>` with `par_boxed_array_init` and turning it into a `Box<[T]>`. Evaluates to `Some` boxed slice, or `None` if `len` isn't one of the supported lengths.
+///
+/// ```rust
+/// # extern crate par_array_init;
+/// use par_array_init::par_array_init_sized;
+///
+/// let len = 16;
+/// let squares: Option<Box<[usize]>> = par_array_init_sized!(len, |i| i * i);
+/// assert_eq!(squares.unwrap()[15], 225);
+/// assert!(par_array_init_sized!(15, |i| i).is_none());
+/// ```
+#[macro_export]
+macro_rules! par_array_init_sized {
+    ($len:expr, $initializer:expr) => {
+        $crate::par_array_init_sized!(@lengths $len, $initializer;
+            0, 1, 2, 4, 8, 16, 32, 64, 128, 256, 512, 1024, 2048, 4096)
+    };
+    (@lengths $len:expr, $initializer:expr; $($n:literal),*) => {{
+        let initializer = $initializer;
+        match $len {
+            $($n => {
+                let boxed: ::std::boxed::Box<[_]> =
+                    $crate::par_boxed_array_init::<_, _, $n>(initializer);
+                ::std::option::Option::Some(boxed)
+            })*
+            _ => ::std::option::Option::None,
+        }
+    }};
+}
+
+/// Initialize a boxed array given a function from index to element.
+///
+/// Shorthand for `par_array_init::<Box<[T; N]>, _>`, the array is allocated once on the heap and written in place.
+#[must_use]
+pub fn par_boxed_array_init<T, F, const N: usize>(initializer: F) -> Box<[T; N]>
+where
+    F: Send + Sync + Fn(usize) -> T,
+    T: Send,
+{
+    par_array_init(initializer)
+}
+
+/// Initialize a boxed array given a function from index to element, keeping each page of the array on one thread.
+///
+/// On NUMA machines a page is placed on the node of the thread that first writes to it. The work is split on the `PAGE_SIZE` boundaries of where the array actually is in memory, which the allocation only aligns to the element type, so every page is filled by a single task: a partial page at the start, whole pages, then a partial page at the end. The pages end up near the thread that filled them.
+/// Each element is filled along with the page it starts on, so only a type whose size doesn't divide `PAGE_SIZE` can have elements straddling two pages' tasks.
+/// The array is written in place on the heap, so it's never moved through the stack.
+#[must_use]
+pub fn par_array_init_first_touch<Array, F>(initializer: F) -> Box<Array>
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send,
+{
+    let size = size_of::<Array::Item>();
+    let span = FillSpan::new(Array::len());
+    let mut ret = Box::<Array>::new_uninit();
+    let slots = uninit_slots(&mut ret);
+    if SEQUENTIAL || size == 0 {
+        // Zero sized elements don't touch any pages
+        init_in_place(&mut ret, initializer, &span);
+        return unsafe { ret.assume_init() };
+    }
+    let len = slots.len();
+    let base = slots.as_ptr() as usize;
+    let first_page = base / PAGE_SIZE;
+    // Index of the first element that starts at or after the start of `page`, counting from the array's first page
+    let page_start = |page: usize| {
+        ((first_page + page) * PAGE_SIZE)
+            .saturating_sub(base)
+            .div_ceil(size)
+            .min(len)
+    };
+    let pages = (base + len * size).div_ceil(PAGE_SIZE) - first_page;
+    let origin = Written::new(slots.as_mut_ptr() as *mut Array::Item);
+    let written = (0..pages)
+        .into_par_iter()
+        .map(|page| {
+            let mut written = origin.empty();
+            for i in page_start(page)..page_start(page + 1) {
+                let _permit = Permit::acquire();
+                written.push(i, initializer(i));
+            }
+            span.chunk(written.start, written.len);
+            written
+        })
+        .reduce(|| origin.empty(), Written::merge);
+    // The pages cover every slot
+    std::mem::forget(written);
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize an array given a function from a shifted, signed index to element, slot `i` receiving `initializer(i + offset)`.
+///
+/// For domains that aren't 0-indexed, e.g. `offset = 1` for 1-based indices. The logical index is signed so negative offsets can go below 0.
+///
+/// # Panics
+///
+/// Panics if a logical index doesn't fit in an `isize`.
+#[must_use]
+pub fn par_array_init_offset<Array, F>(offset: isize, initializer: F) -> Array
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(isize) -> Array::Item,
+    Array::Item: Send,
+{
+    use std::convert::TryFrom;
+
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    init_in_place(
+        &mut ret,
+        |i| {
+            let logical = isize::try_from(i)
+                .ok()
+                .and_then(|i| i.checked_add(offset))
+                .expect("par_array_init_offset: logical index overflows isize");
+            initializer(logical)
+        },
+        &span,
+    );
+    // Every slot was written
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize an array given a function from logical index to element, storing logical index `i` at slot `(start + i) % N`.
+///
+/// For ring buffers that start partway through their storage. `start` wraps around, so any value is accepted.
+#[must_use]
+pub fn par_array_init_rotated<Array, F>(start: usize, initializer: F) -> Array
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send,
+{
+    let len = Array::len();
+    let start = start.checked_rem(len).unwrap_or(0);
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    // Slot j holds logical index j - start, wrapped back into the array without overflowing
+    init_in_place(
+        &mut ret,
+        |slot| {
+            let i = if slot >= start {
+                slot - start
+            } else {
+                slot + (len - start)
+            };
+            initializer(i)
+        },
+        &span,
+    );
+    // Every slot was written
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize an array given a function from index to element, counting the cache misses and instructions of the fill with Linux perf events.
+///
+/// Requires the `perf` feature and Linux, and is best effort: every thread of the pool opens its own counters through `rayon::broadcast`, they're enabled only around the fill, and the totals are summed over every thread. A count is `None` if any thread couldn't open its counter, e.g. without a hardware PMU or when `perf_event_paranoid` forbids it, rather than an undercount.
+/// Counters only see the pool's threads, so work a closure hands to other threads isn't counted.
+#[cfg(all(feature = "perf", target_os = "linux"))]
+#[must_use]
+pub fn par_array_init_perf<Array, F>(initializer: F) -> (Array, PerfCounters)
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send,
+{
+    use perf_event::events::Hardware;
+
+    // A counter observes the thread that opened it, but can be enabled and read from any thread
+    let open = |kind: Hardware| perf_event::Builder::new().kind(kind).build().ok();
+    let open_both = |_| (open(Hardware::CACHE_MISSES), open(Hardware::INSTRUCTIONS));
+    // A sequential fill only runs on this thread, so that's the only one that needs counting
+    let threads = if SEQUENTIAL {
+        vec![open_both(())]
+    } else {
+        rayon::broadcast(|_| open_both(()))
+    };
+    let mut counters: Vec<_> = threads
+        .into_iter()
+        .flat_map(|(misses, instructions)| [misses, instructions])
+        .collect();
+    for counter in counters.iter_mut().flatten() {
+        let _ = counter.enable();
+    }
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    init_in_place(&mut ret, initializer, &span);
+    let mut total = |kind: usize| -> Option<u64> {
+        counters
+            .iter_mut()
+            .skip(kind)
+            .step_by(2)
+            .try_fold(0, |sum, counter| {
+                let counter = counter.as_mut()?;
+                counter.disable().ok()?;
+                Some(sum + counter.read().ok()?)
+            })
+    };
+    let counts = PerfCounters {
+        cache_misses: total(0),
+        instructions: total(1),
+    };
+    // Every slot was written
+    (unsafe { ret.assume_init() }, counts)
+}
+
+/// Event counts of a fill, returned by `par_array_init_perf`.
+#[cfg(all(feature = "perf", target_os = "linux"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PerfCounters {
+    /// Cache misses over every thread, or `None` if they couldn't be counted.
+    pub cache_misses: Option<u64>,
+    /// Instructions retired over every thread, or `None` if they couldn't be counted.
+    pub instructions: Option<u64>,
+}
+
+/// Initialize an array given a function from index to element, also measuring how evenly the work was spread over the pool's threads.
+///
+/// Each thread adds up the time it spends running `initializer`, and the returned metric is the busiest thread's total divided by the mean over every thread in the pool. Values near 1.0 mean the work was balanced, a value near the thread count means one thread did nearly all of it.
+/// Timing every call adds some overhead, so this is meant for tuning rather than production fills.
+#[must_use]
+pub fn par_array_init_imbalance<Array, F>(initializer: F) -> (Array, f64)
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send,
+{
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    let busy: Vec<AtomicU64> = (0..rayon::current_num_threads())
+        .map(|_| AtomicU64::new(0))
+        .collect();
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    init_in_place(
+        &mut ret,
+        |i| {
+            let start = std::time::Instant::now();
+            let elem = initializer(i);
+            let thread = rayon::current_thread_index().unwrap_or(0);
+            busy[thread].fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+            elem
+        },
+        &span,
+    );
+    // Every slot was written
+    let array = unsafe { ret.assume_init() };
+    let busy: Vec<u64> = busy.into_iter().map(AtomicU64::into_inner).collect();
+    let max = busy.iter().copied().max().unwrap_or(0);
+    let total: u64 = busy.iter().sum();
+    let imbalance = if total == 0 {
+        1.0
+    } else {
+        max as f64 * busy.len() as f64 / total as f64
+    };
+    (array, imbalance)
+}
+
+/// Initialize an array given a function from index to element, also counting how many elements fall in each of `buckets` buckets.
+///
+/// `bucket_of(&elem)` is called on each element right after it's computed by the same task, and entry `b` of the returned histogram is the number of elements it put in bucket `b`.
+///
+/// # Panics
+///
+/// Panics if `bucket_of` returns a bucket that isn't below `buckets`.
+#[must_use]
+pub fn par_array_init_histogram<Array, F, B>(
+    initializer: F,
+    buckets: usize,
+    bucket_of: B,
+) -> (Array, Vec<usize>)
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    B: Send + Sync + Fn(&Array::Item) -> usize,
+    Array::Item: Send,
+{
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let counts: Vec<AtomicUsize> = (0..buckets).map(|_| AtomicUsize::new(0)).collect();
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    init_in_place(
+        &mut ret,
+        |i| {
+            let elem = initializer(i);
+            let bucket = bucket_of(&elem);
+            let count = counts.get(bucket).unwrap_or_else(|| {
+                panic!(
+                    "par_array_init_histogram: bucket {} is out of range for {} buckets",
+                    bucket, buckets
+                )
+            });
+            count.fetch_add(1, Ordering::Relaxed);
+            elem
+        },
+        &span,
+    );
+    // Every slot was written
+    let array = unsafe { ret.assume_init() };
+    (
+        array,
+        counts.into_iter().map(AtomicUsize::into_inner).collect(),
+    )
+}
+
+/// Initialize an array given a function from index to element, also encoding every element with `encode` in the same parallel pass.
+///
+/// Entry `i` of the returned encodings is `encode(&array[i])`, computed right after element `i` by the same task, so serialization doesn't need a second pass over the array.
+#[must_use]
+pub fn par_array_init_encoded<Array, F, Enc>(initializer: F, encode: Enc) -> (Array, Vec<Vec<u8>>)
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Enc: Send + Sync + Fn(&Array::Item) -> Vec<u8>,
+    Array::Item: Send,
+{
+    // Empty Vecs don't allocate, and owning initialized entries means finished encodings are dropped on a panic
+    let mut encodings = vec![Vec::new(); Array::len()];
+    // Each index replaces its own entry
+    let entries = SendPtr(encodings.as_mut_ptr());
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    init_in_place(
+        &mut ret,
+        |i| {
+            let elem = initializer(i);
+            unsafe { *entries.add(i) = encode(&elem) };
+            elem
+        },
+        &span,
+    );
+    // Every slot was written
+    (unsafe { ret.assume_init() }, encodings)
+}
+
+/// Initialize an array given a function from index to element, also recording how long each call to `initializer` took.
+///
+/// Element `i` of the returned durations is the time `initializer(i)` ran for. The durations are written in the same parallel pass as the elements, so this is cheap enough to find hot indices with.
+#[must_use]
+pub fn par_array_init_timed<T, F, const N: usize>(
+    initializer: F,
+) -> ([T; N], [std::time::Duration; N])
+where
+    T: Send,
+    F: Send + Sync + Fn(usize) -> T,
+{
+    let mut durations = MaybeUninit::<[std::time::Duration; N]>::uninit();
+    // Each index writes its own slot
+    let timings = SendPtr(durations.as_mut_ptr() as *mut std::time::Duration);
+    let array = par_array_init(|i| {
+        let start = std::time::Instant::now();
+        let elem = initializer(i);
+        unsafe { timings.add(i).write(start.elapsed()) };
+        elem
+    });
+    // Every duration was written along with its element
+    (array, unsafe { durations.assume_init() })
+}
+
+/// How many indices ahead of the element being computed `par_array_init_prefetch` asks for a prefetch.
+pub const PREFETCH_DISTANCE: usize = 8;
+
+/// Initialize an array given a function from index to element, calling `prefetch(i + PREFETCH_DISTANCE)` before computing element `i`.
+///
+/// For closures that read from another large structure, `prefetch` can issue prefetch hints (e.g. `core::arch` intrinsics) for the data a later index will need, hiding some of the memory latency. Prefetching is best-effort: `prefetch` is only called for indices inside the array, and the earliest indices of each task are never prefetched.
+#[must_use]
+pub fn par_array_init_prefetch<Array, F, P>(initializer: F, prefetch: P) -> Array
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    P: Send + Sync + Fn(usize),
+    Array::Item: Send,
+{
+    let len = Array::len();
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    init_in_place(
+        &mut ret,
+        |i| {
+            if let Some(ahead) = i
+                .checked_add(PREFETCH_DISTANCE)
+                .filter(|&ahead| ahead < len)
+            {
+                prefetch(ahead);
+            }
+            initializer(i)
+        },
+        &span,
+    );
+    // Every slot was written
+    unsafe { ret.assume_init() }
+}
+
+/// Page size assumed by `par_array_init_first_touch`.
+pub const PAGE_SIZE: usize = 4096;
+
+/// Upgrade an array of weak references in parallel, computing a fresh `Arc` with `default(i)` for every one that's no longer alive.
+///
+/// For caches of weak references: live entries are shared as they are and only dead ones are rebuilt.
+#[must_use]
+pub fn par_array_init_upgrade<T, D, const N: usize>(
+    weaks: &[std::sync::Weak<T>; N],
+    default: D,
+) -> [std::sync::Arc<T>; N]
+where
+    T: Send + Sync,
+    D: Send + Sync + Fn(usize) -> std::sync::Arc<T>,
+{
+    par_array_init(|i| weaks[i].upgrade().unwrap_or_else(|| default(i)))
+}
+
+/// Re-initialize an existing array in place given a function from index to element, dropping the old elements.
+pub fn par_overwrite<Array, F>(array: &mut Array, initializer: F)
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send,
+{
+    par_overwrite_with(array, initializer, drop);
+}
+
+/// Re-initialize an existing array in place given a function from index to element, handing each displaced element to `on_drop`.
+///
+/// Instead of being dropped, each old element is passed to `on_drop` on the thread that replaced it, so callers can recycle resources like buffers.
+pub fn par_overwrite_with<Array, F, D>(array: &mut Array, initializer: F, on_drop: D)
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    D: Send + Sync + Fn(Array::Item),
+    Array::Item: Send,
+{
+    let _span = FillSpan::new(Array::len());
+    let min_len = default_min_len::<Array::Item>(Array::len());
+    let elems = array.mut_slice().par_iter_mut().with_min_len(min_len);
+    par_for_each(elems.enumerate(), |(i, elem)| {
+        let old = {
+            let _permit = Permit::acquire();
+            std::mem::replace(elem, initializer(i))
+        };
+        on_drop(old)
+    });
+}
+
+/// A container of elements that can be overwritten in parallel by index.
+///
+/// `par_init_into` fills any implementor the same way, so arrays, `Vec`s and boxed slices can share one code path.
+pub trait ParIndexedWrite {
+    /// Type of the container's elements.
+    type Item;
+    /// Number of elements in the container.
+    fn len(&self) -> usize;
+    /// Whether the container has no elements.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Replace element `i` with `initializer(i)` for every index, in parallel, dropping the old elements.
+    fn par_write_each<F>(&mut self, initializer: F)
+    where
+        F: Send + Sync + Fn(usize) -> Self::Item,
+        Self::Item: Send;
+}
+
+/// Replace every element of `slice` with `initializer(i)` in parallel.
+fn par_write_slice<T, F>(slice: &mut [T], initializer: F)
+where
+    T: Send,
+    F: Send + Sync + Fn(usize) -> T,
+{
+    let _span = FillSpan::new(slice.len());
+    let min_len = default_min_len::<T>(slice.len());
+    let elems = slice.par_iter_mut().with_min_len(min_len);
+    par_for_each(elems.enumerate(), |(i, elem)| {
+        let _permit = Permit::acquire();
+        *elem = initializer(i)
+    });
+}
+
+impl<T, const N: usize> ParIndexedWrite for [T; N] {
+    type Item = T;
+
+    fn len(&self) -> usize {
+        N
+    }
+
+    fn par_write_each<F>(&mut self, initializer: F)
+    where
+        F: Send + Sync + Fn(usize) -> T,
+        T: Send,
+    {
+        par_write_slice(self, initializer);
+    }
+}
+
+impl<T> ParIndexedWrite for Vec<T> {
+    type Item = T;
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn par_write_each<F>(&mut self, initializer: F)
+    where
+        F: Send + Sync + Fn(usize) -> T,
+        T: Send,
+    {
+        par_write_slice(self, initializer);
+    }
+}
+
+impl<T> ParIndexedWrite for Box<[T]> {
+    type Item = T;
+
+    fn len(&self) -> usize {
+        <[T]>::len(self)
+    }
+
+    fn par_write_each<F>(&mut self, initializer: F)
+    where
+        F: Send + Sync + Fn(usize) -> T,
+        T: Send,
+    {
+        par_write_slice(self, initializer);
+    }
+}
+
+/// Overwrite every element of `slice` in parallel given a function from index to element, dropping the old elements.
+///
+/// For buffers the caller already owns and that hold valid elements; to fill uninitialized memory use `par_fill_uninit` instead.
+pub fn par_reinit_slice<T, F>(slice: &mut [T], initializer: F)
+where
+    T: Send,
+    F: Send + Sync + Fn(usize) -> T,
+{
+    par_write_slice(slice, initializer);
+}
+
+/// Overwrite every element of `target` given a function from index to element.
+///
+/// `target` keeps its length, see `ParIndexedWrite` for the containers this works on.
+pub fn par_init_into<W, F>(target: &mut W, initializer: F)
+where
+    W: ParIndexedWrite,
+    F: Send + Sync + Fn(usize) -> W::Item,
+    W::Item: Send,
+{
+    target.par_write_each(initializer);
+}
+
+/// Re-initialize an existing array given a function from index to element, only writing slots whose value changed.
+///
+/// Each proposed value is compared against the current element and only replaces it (dropping the old element) when they differ. Returns the number of slots that changed.
+pub fn par_array_init_if_changed<Array, F>(array: &mut Array, initializer: F) -> usize
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send + PartialEq,
+{
+    let _span = FillSpan::new(Array::len());
+    let update = |(i, elem): (usize, &mut Array::Item)| {
+        let new = {
+            let _permit = Permit::acquire();
+            initializer(i)
+        };
+        if *elem == new {
+            0
+        } else {
+            *elem = new;
+            1
+        }
+    };
+    let elems = array.mut_slice().par_iter_mut().enumerate();
+    if SEQUENTIAL {
+        let mut changed = 0;
+        for_each_in_order(elems, |elem| changed += update(elem));
+        changed
+    } else {
+        elems.map(update).sum()
+    }
+}
+
+/// Recompute only the elements of an existing array whose bit is set in `dirty`, given a function from index to element.
+///
+/// Requires the `bitvec` feature. For incremental updates where only a few inputs changed: `initializer(i)` is called in parallel for each set bit `i`, dropping the old element, and every other element is left as it is. Bits past the end of the array are ignored.
+///
+/// # Panics
+///
+/// Panics if the `W` words have fewer bits than the array has elements.
+#[cfg(feature = "bitvec")]
+pub fn par_array_update_dirty<Array, F, const W: usize>(
+    array: &mut Array,
+    dirty: &bitvec::array::BitArray<[usize; W]>,
+    initializer: F,
+) where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send,
+{
+    assert!(
+        Array::len() <= W * usize::BITS as usize,
+        "par_array_update_dirty: bit array is too small for the array"
+    );
+    let _span = FillSpan::new(Array::len());
+    par_for_each(array.mut_slice().par_iter_mut().enumerate(), |(i, elem)| {
+        if dirty[i] {
+            let _permit = Permit::acquire();
+            *elem = initializer(i);
+        }
+    });
+}
+
+/// Compute the elements of an `Array::len()` element array given a function from index to element, routing each into one of `groups` vectors by `key(index)`.
+///
+/// For partitioning while building. Every task collects its elements into its own groups, which are appended to each other in index order afterwards, so each group holds its elements in ascending index order without any locking.
+///
+/// # Panics
+///
+/// Panics if `key` returns an index that isn't below `groups`.
+#[must_use]
+pub fn par_array_init_grouped<Array, F, K>(
+    initializer: F,
+    key: K,
+    groups: usize,
+) -> Vec<Vec<Array::Item>>
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    K: Send + Sync + Fn(usize) -> usize,
+    Array::Item: Send,
+{
+    let len = Array::len();
+    let _span = FillSpan::new(len);
+    let empty = || (0..groups).map(|_| Vec::new()).collect::<Vec<_>>();
+    let route = |mut grouped: Vec<Vec<Array::Item>>, i| {
+        let group = key(i);
+        assert!(
+            group < groups,
+            "par_array_init_grouped: key {} is out of range for {} groups",
+            group,
+            groups
+        );
+        let _permit = Permit::acquire();
+        grouped[group].push(initializer(i));
+        grouped
+    };
+    if SEQUENTIAL {
+        return (0..len).fold(empty(), route);
+    }
+    (0..len)
+        .into_par_iter()
+        .with_min_len(default_min_len::<Array::Item>(len))
+        .fold(empty, route)
+        .reduce(empty, |mut left, right| {
+            for (left, right) in left.iter_mut().zip(right) {
+                left.extend(right);
+            }
+            left
+        })
+}
+
+/// Initialize an array given a function from index to a run of elements, for `0..count`.
+///
+/// The runs `initializer(0), initializer(1), ..., initializer(count - 1)` are generated in parallel and laid end to end, so they have to add up to exactly `Array::len()` elements. Returns None if they don't.
+#[must_use]
+pub fn par_array_init_flat_map<Array, F, It>(count: usize, initializer: F) -> Option<Array>
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> It,
+    It: IntoIterator<Item = Array::Item>,
+    Array::Item: Send,
+{
+    let _span = FillSpan::new(Array::len());
+    let runs: Vec<Vec<Array::Item>> = par_map_collect((0..count).into_par_iter(), |i| {
+        let _permit = Permit::acquire();
+        initializer(i).into_iter().collect()
+    });
+    if runs.iter().map(Vec::len).sum::<usize>() != Array::len() {
+        return None;
+    }
+    let mut ret = MaybeUninit::<Array>::uninit();
+    // Hand each run the slots it covers, every element is generated already so moving them in can't panic
+    let mut rest = uninit_slots(&mut ret);
+    let mut targets = Vec::with_capacity(runs.len());
+    for run in runs {
+        let (head, tail) = rest.split_at_mut(run.len());
+        targets.push((head, run));
+        rest = tail;
+    }
+    par_for_each(targets.into_par_iter(), |(slots, run)| {
+        for (slot, elem) in slots.iter_mut().zip(run) {
+            slot.write(elem);
+        }
+    });
+    // The runs add up to Array::len() so every slot was written
+    Some(unsafe { ret.assume_init() })
+}
+
+/// Initialize an array given a function from index to element, caching the result in the file at `path`.
+///
+/// Requires the `serde` feature. If `path` holds an array of the same element type and length it's loaded instead of calling `initializer`, otherwise the array is computed in parallel and written to `path` for next time.
+/// A cache that can't be read or was written for a different element type or length is treated as missing. Returns an error if the cache can't be written back.
+#[cfg(feature = "serde")]
+pub fn par_array_init_cached<Array, F, P>(path: P, initializer: F) -> std::io::Result<Array>
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send + serde::Serialize + serde::de::DeserializeOwned,
+    P: AsRef<std::path::Path>,
+{
+    let path = path.as_ref();
+    let span = FillSpan::new(Array::len());
+    if let Some(array) = load_cached(path, &span) {
+        return Ok(array);
+    }
+    let mut ret = MaybeUninit::<Array>::uninit();
+    init_in_place(&mut ret, initializer, &span);
+    let mut array = unsafe { ret.assume_init() };
+    let header = (std::any::type_name::<Array::Item>(), Array::len());
+    let bytes =
+        bincode::serialize(&(header, &*array.mut_slice())).map_err(std::io::Error::other)?;
+    std::fs::write(path, bytes)?;
+    Ok(array)
+}
+
+/// Load an array cached by `par_array_init_cached`, returns None if the cache is missing or stale.
+#[cfg(feature = "serde")]
+fn load_cached<Array>(path: &std::path::Path, span: &FillSpan) -> Option<Array>
+where
+    Array: IsParArray,
+    Array::Item: Send + serde::de::DeserializeOwned,
+{
+    let bytes = std::fs::read(path).ok()?;
+    let ((type_name, len), items): ((String, usize), Vec<Array::Item>) =
+        bincode::deserialize(&bytes).ok()?;
+    if type_name != std::any::type_name::<Array::Item>()
+        || len != Array::len()
+        || items.len() != len
+    {
+        return None;
+    }
+    let mut ret = MaybeUninit::<Array>::uninit();
+    fill_par_iter(uninit_slots(&mut ret), items.into_par_iter(), span);
+    Some(unsafe { ret.assume_init() })
+}
+
+/// Initialize an array given a function from index to element, reducing the elements to a summary in the same pass.
+///
+/// Each element is combined with `reduce` as it's written, starting from `identity`, so e.g. a sum or max comes for free rather than needing a second pass over the array. `identity` has to be an identity of `reduce`, and `reduce` should be associative.
+/// The reduction is deterministic: elements are reduced in index order within fixed runs of `REDUCE_CHUNK_LEN`, and the runs' results are combined in index order, so the summary doesn't depend on the number of threads or how the work was scheduled. That matters for operations like floating point addition that only approximately associate.
+#[must_use]
+pub fn par_array_init_with_reduce<Array, F, R>(
+    initializer: F,
+    identity: Array::Item,
+    reduce: R,
+) -> (Array, Array::Item)
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    R: Send + Sync + Fn(Array::Item, Array::Item) -> Array::Item,
+    Array::Item: Send + Sync + Clone,
+{
+    let len = Array::len();
+    let span = FillSpan::new(len);
+    let mut ret = MaybeUninit::<Array>::uninit();
+    let origin = Written::new(uninit_slots(&mut ret).as_mut_ptr() as *mut Array::Item);
+    let runs = par_map_collect((0..len.div_ceil(REDUCE_CHUNK_LEN)).into_par_iter(), |run| {
+        let mut written = origin.empty();
+        let mut acc = identity.clone();
+        let start = run * REDUCE_CHUNK_LEN;
+        for i in start..start.saturating_add(REDUCE_CHUNK_LEN).min(len) {
+            let _permit = Permit::acquire();
+            let elem = initializer(i);
+            acc = reduce(acc, elem.clone());
+            written.push(i, elem);
+        }
+        span.chunk(written.start, written.len);
+        (written, acc)
+    });
+    let mut written = origin.empty();
+    let mut summary = identity;
+    for (run, acc) in runs {
+        written = written.merge(run);
+        summary = reduce(summary, acc);
+    }
+    // The runs cover every slot
+    std::mem::forget(written);
+    (unsafe { ret.assume_init() }, summary)
+}
+
+/// Number of elements `par_array_init_with_reduce` reduces sequentially before combining with other runs.
+pub const REDUCE_CHUNK_LEN: usize = 1024;
+
+/// Initialize an array given a function from index to element, passing each element through `repair` before it's placed.
+///
+/// `repair` is given the index and freshly computed element and returns the element to store, e.g. clamping it into range. It runs on the same thread right after `initializer`, so computing and fixing up each element is a single parallel pass.
+#[must_use]
+pub fn par_array_init_repair<Array, F, R>(initializer: F, repair: R) -> Array
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    R: Send + Sync + Fn(usize, Array::Item) -> Array::Item,
+    Array::Item: Send,
+{
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    init_in_place(&mut ret, |i| repair(i, initializer(i)), &span);
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize an array given a function from shared per-thread state and index to element.
+///
+/// `make_state` runs once on every thread of the current pool via `rayon::broadcast`, and each thread passes its own state to `initializer` for every index it handles. This amortizes expensive setup, like building a lookup table, across all of a thread's elements.
+#[must_use]
+pub fn par_array_init_broadcast<Out, S, M, F>(make_state: M, initializer: F) -> Out
+where
+    Out: FromParallelArray,
+    S: Send + Sync,
+    M: Sync + Fn() -> S,
+    F: Send + Sync + Fn(&S, usize) -> Out::Item,
+    Out::Item: Send,
+{
+    if SEQUENTIAL {
+        // The fill only runs on this thread, so it only needs one state
+        let state = make_state();
+        return par_array_init(|i| initializer(&state, i));
+    }
+    let states = rayon::broadcast(|_| make_state());
+    par_array_init(|i| {
+        // Fills run on the same pool we broadcast to, so every worker has a state
+        let thread = rayon::current_thread_index().unwrap_or(0);
+        initializer(&states[thread], i)
+    })
+}
+
+/// Initialize an array given a function from `Index` to element.
+///
+/// The same as `par_array_init`, but the index is wrapped in a newtype so it can't be mixed up with the other integers in complex index logic.
+#[must_use]
+pub fn par_array_init_typed_index<Out, F>(initializer: F) -> Out
+where
+    Out: FromParallelArray,
+    F: Send + Sync + Fn(Index) -> Out::Item,
+    Out::Item: Send,
+{
+    par_array_init(|i| initializer(Index(i)))
+}
+
+/// Index of the element being initialized, passed to `par_array_init_typed_index`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Index(usize);
+
+impl Index {
+    /// The index as a `usize`.
+    pub fn get(self) -> usize {
+        self.0
+    }
+
+    /// The index before this one, or this index if it's already 0.
+    pub fn saturating_prev(self) -> Index {
+        Index(self.0.saturating_sub(1))
+    }
+}
+
+/// Initialize an array given a function from mutable scratch space and index to element.
+///
+/// Unlike `par_array_init_broadcast` the scratch is handed out mutably, so `initializer` can reuse a buffer across calls instead of allocating one per element. Scratch is created with `make_scratch` through rayon's `map_init`, once per task of the fill rather than once per element, and is never shared between threads.
+#[must_use]
+pub fn par_array_init_scratch<Array, Scratch, M, F>(make_scratch: M, initializer: F) -> Array
+where
+    Array: IsParArray,
+    M: Send + Sync + Fn() -> Scratch,
+    F: Send + Sync + Fn(&mut Scratch, usize) -> Array::Item,
+    Array::Item: Send,
+{
+    let len = Array::len();
+    let iter = (0..len)
+        .into_par_iter()
+        .with_min_len(default_min_len::<Array::Item>(len))
+        .map_init(make_scratch, |scratch, i| {
+            let _permit = Permit::acquire();
+            initializer(scratch, i)
+        });
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    fill_par_iter(uninit_slots(&mut ret), iter, &span);
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize an array given a function from a pooled object and index to element, so expensive objects like buffers are reused across calls.
+///
+/// Every call gets an object from `pool` and it goes back to the pool when the `PooledRef` is dropped, so the pool only ever creates as many objects as were in use at once. Use `PooledRef::detach` to keep an object out of the pool, e.g. to make it the element itself.
+#[must_use]
+pub fn par_array_init_pooled<Array, P, F>(pool: &ObjectPool<P>, initializer: F) -> Array
+where
+    Array: IsParArray,
+    P: Send,
+    F: Send + Sync + Fn(PooledRef<'_, P>, usize) -> Array::Item,
+    Array::Item: Send,
+{
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    init_in_place(&mut ret, |i| initializer(pool.get(), i), &span);
+    unsafe { ret.assume_init() }
+}
+
+/// A pool of reusable objects shared between threads, handed out by `get` and used by `par_array_init_pooled`.
+pub struct ObjectPool<T> {
+    free: std::sync::Mutex<Vec<T>>,
+    make: Box<dyn Fn() -> T + Send + Sync>,
+    created: std::sync::atomic::AtomicUsize,
+}
+
+impl<T> ObjectPool<T> {
+    /// Create an empty pool that makes new objects with `make` whenever none are free.
+    pub fn new<M>(make: M) -> Self
+    where
+        M: Fn() -> T + Send + Sync + 'static,
+    {
+        ObjectPool {
+            free: std::sync::Mutex::new(Vec::new()),
+            make: Box::new(make),
+            created: std::sync::atomic::AtomicUsize::new(0),
+        }
+    }
+
+    /// Take a free object out of the pool, or make a new one if there are none.
+    ///
+    /// Objects come back as they were left, so clear them before use if that matters.
+    pub fn get(&self) -> PooledRef<'_, T> {
+        use std::sync::atomic::Ordering;
+
+        // A panic while holding the lock can't leave the free list half updated
+        let free = self.free.lock().unwrap_or_else(|e| e.into_inner()).pop();
+        let item = free.unwrap_or_else(|| {
+            self.created.fetch_add(1, Ordering::Relaxed);
+            (self.make)()
+        });
+        PooledRef {
+            pool: self,
+            item: Some(item),
+        }
+    }
+
+    /// Number of objects the pool has made so far.
+    pub fn created(&self) -> usize {
+        self.created.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+impl<T> std::fmt::Debug for ObjectPool<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ObjectPool")
+            .field("created", &self.created())
+            .finish_non_exhaustive()
+    }
+}
+
+/// An object borrowed from an `ObjectPool`, given back to the pool when it's dropped.
+pub struct PooledRef<'a, T> {
+    pool: &'a ObjectPool<T>,
+    // Only None once detached or dropped
+    item: Option<T>,
+}
+
+impl<T> PooledRef<'_, T> {
+    /// Take the object out of the pool for good instead of giving it back.
+    pub fn detach(mut self) -> T {
+        self.item
+            .take()
+            .unwrap_or_else(|| unreachable!("par_array_init: pooled object was already taken"))
+    }
+}
+
+impl<T> std::ops::Deref for PooledRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.item
+            .as_ref()
+            .unwrap_or_else(|| unreachable!("par_array_init: pooled object was already taken"))
+    }
+}
+
+impl<T> std::ops::DerefMut for PooledRef<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.item
+            .as_mut()
+            .unwrap_or_else(|| unreachable!("par_array_init: pooled object was already taken"))
+    }
+}
+
+impl<T> Drop for PooledRef<'_, T> {
+    fn drop(&mut self) {
+        if let Some(item) = self.item.take() {
+            let mut free = self.pool.free.lock().unwrap_or_else(|e| e.into_inner());
+            free.push(item);
+        }
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for PooledRef<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("PooledRef").field(&**self).finish()
+    }
+}
+
+/// Initialize an array given a function from a shared context and index to element, with the context owned through an `Arc`.
+///
+/// The owned counterpart to capturing a borrowed context in the closure, for when the context can't outlive the call's borrows. `ctx` is cloned through rayon's `map_with`, once per task of the fill rather than once per element, so the reference count isn't contended on every call.
+#[must_use]
+pub fn par_array_init_arc_ctx<Array, C, F>(ctx: std::sync::Arc<C>, initializer: F) -> Array
+where
+    Array: IsParArray,
+    C: Send + Sync,
+    F: Send + Sync + Fn(&C, usize) -> Array::Item,
+    Array::Item: Send,
+{
+    let len = Array::len();
+    let iter = (0..len)
+        .into_par_iter()
+        .with_min_len(default_min_len::<Array::Item>(len))
+        .map_with(ctx, |ctx, i| {
+            let _permit = Permit::acquire();
+            initializer(ctx, i)
+        });
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    fill_par_iter(uninit_slots(&mut ret), iter, &span);
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize an array given a function from index to element, wrapped in `ManuallyDrop` so it's never dropped by Rust.
+///
+/// For handing ownership of an array across FFI, where the other side decides when it's freed. If it's handed back to Rust to free, `reclaim_manually_drop` turns the pointer to it back into an array that drops normally.
+#[must_use]
+pub fn par_array_init_manually_drop<Array, F>(initializer: F) -> std::mem::ManuallyDrop<Array>
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send,
+{
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    init_in_place(&mut ret, initializer, &span);
+    std::mem::ManuallyDrop::new(unsafe { ret.assume_init() })
+}
+
+/// Move an array out from behind a raw pointer, such as one to a `ManuallyDrop` array handed back across FFI, so it's dropped normally.
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads, aligned, and point to an initialized array that nothing else will drop or use afterwards, since ownership moves to the returned array.
+pub unsafe fn reclaim_manually_drop<Array>(ptr: *mut std::mem::ManuallyDrop<Array>) -> Array {
+    std::mem::ManuallyDrop::into_inner(std::ptr::read(ptr))
+}
+
+/// Initialize an array where each element can depend on the `window` elements before it.
+///
+/// `initializer` is given the already computed elements `array[i - window..i]` (fewer near the start) along with the index `i`.
+/// With a `window` of atleast 1 every element depends on the one directly before it, so the dependencies form a single chain and the elements are computed in index order on the calling thread. With a `window` of 0 the elements are independent and are computed in parallel like `par_array_init`, each given an empty slice.
+#[must_use]
+pub fn par_array_init_recurrence<Array, F>(window: usize, initializer: F) -> Array
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(&[Array::Item], usize) -> Array::Item,
+    Array::Item: Send,
+{
+    let mut ret = MaybeUninit::<Array>::uninit();
+    if window == 0 {
+        init_in_place(
+            &mut ret,
+            |i| initializer(&[], i),
+            &FillSpan::new(Array::len()),
+        );
+        return unsafe { ret.assume_init() };
+    }
+    let span = FillSpan::with_threads(Array::len(), 1);
+    let slots = uninit_slots(&mut ret);
+    let mut written = Written::new(slots.as_mut_ptr() as *mut Array::Item);
+    for i in 0..Array::len() {
+        let start = i.saturating_sub(window);
+        // written covers exactly 0..i
+        let previous = unsafe { std::slice::from_raw_parts(written.base.add(start), i - start) };
+        let _permit = Permit::acquire();
+        let elem = initializer(previous, i);
+        written.push(i, elem);
+    }
+    span.chunk(0, written.len);
+    std::mem::forget(written);
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize an array where each element can depend on any other elements, given a function from index to the indices it depends on.
+///
+/// Generalizes `par_array_init_recurrence` from a chain to a DAG. `deps` is called once per index, then the elements are computed in waves: every element whose dependencies are all computed is computed in parallel, before moving on to the elements that were waiting on them.
+/// `initializer` is given every slot of the array along with the index `i`, the slots of `i`'s dependencies are always `Some`, other slots may or may not be.
+/// Returns an error without calling `initializer` if the dependencies contain a cycle, including an index depending on itself.
+///
+/// # Panics
+///
+/// Panics if `deps` returns an index past the end of the array.
+pub fn par_array_init_dag<Array, D, F>(deps: D, initializer: F) -> Result<Array, CycleError>
+where
+    Array: IsParArray,
+    D: Send + Sync + Fn(usize) -> Vec<usize>,
+    F: Send + Sync + Fn(&[Option<Array::Item>], usize) -> Array::Item,
+    Array::Item: Send + Sync,
+{
+    let len = Array::len();
+    let deps: Vec<Vec<usize>> = par_map_collect((0..len).into_par_iter(), deps);
+    let mut waiting_on = vec![0; len];
+    let mut dependents = vec![Vec::new(); len];
+    for (i, deps) in deps.iter().enumerate() {
+        for &dep in deps {
+            assert!(
+                dep < len,
+                "par_array_init: {} depends on {}, past the end of an array of length {}",
+                i,
+                dep,
+                len
+            );
+            waiting_on[i] += 1;
+            dependents[dep].push(i);
+        }
+    }
+
+    let mut waves = Vec::new();
+    let mut wave: Vec<usize> = (0..len).filter(|&i| waiting_on[i] == 0).collect();
+    let mut scheduled = 0;
+    while !wave.is_empty() {
+        scheduled += wave.len();
+        let mut next = Vec::new();
+        for &i in &wave {
+            for &dependent in &dependents[i] {
+                waiting_on[dependent] -= 1;
+                if waiting_on[dependent] == 0 {
+                    next.push(dependent);
+                }
+            }
+        }
+        waves.push(std::mem::replace(&mut wave, next));
+    }
+    if scheduled != len {
+        // Every unscheduled index waits on another unscheduled index, so following those from any of them has to loop
+        let mut seen = vec![false; len];
+        let mut index = (0..len)
+            .find(|&i| waiting_on[i] != 0)
+            .unwrap_or_else(|| unreachable!("par_array_init: an unscheduled index exists"));
+        while !std::mem::replace(&mut seen[index], true) {
+            index = *deps[index]
+                .iter()
+                .find(|&&dep| waiting_on[dep] != 0)
+                .unwrap_or_else(|| {
+                    unreachable!(
+                        "par_array_init: an unscheduled index waits on another unscheduled index"
+                    )
+                });
+        }
+        return Err(CycleError { index });
+    }
+
+    let _span = FillSpan::new(len);
+    let mut slots: Vec<Option<Array::Item>> = (0..len).map(|_| None).collect();
+    for wave in waves {
+        let elems = par_map_collect(wave.par_iter(), |&i| {
+            let _permit = Permit::acquire();
+            initializer(&slots, i)
+        });
+        for (i, elem) in wave.into_iter().zip(elems) {
+            slots[i] = Some(elem);
+        }
+    }
+    let elems = slots.into_par_iter().map(|slot| {
+        slot.unwrap_or_else(|| unreachable!("par_array_init: every index is in a wave"))
+    });
+    // Moving the elements into place is part of this fill's span
+    let mut ret = MaybeUninit::<Array>::uninit();
+    fill_par_iter(uninit_slots(&mut ret), elems, &FillSpan::nested());
+    Ok(unsafe { ret.assume_init() })
+}
+
+/// Error returned by `par_array_init_dag` when the dependencies contain a cycle.
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CycleError {
+    /// An index on the cycle.
+    pub index: usize,
+}
+
+impl std::fmt::Display for CycleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "element {} depends on itself through a cycle",
+            self.index
+        )
+    }
+}
+
+impl std::error::Error for CycleError {}
+
+/// Initialize an array given a seed and a function from seed and index to element.
+///
+/// This behaves like unfolding a sequence from `seed`, but `initializer` has to compute element `i` from the seed and `i` alone, e.g. by jumping a generator ahead `i` steps, rather than from the element before it. That purity is what lets every element be computed in parallel, use `par_array_init_recurrence` when elements really do depend on earlier ones.
+#[must_use]
+pub fn par_array_init_unfold<Array, S, F>(seed: S, initializer: F) -> Array
+where
+    Array: IsParArray,
+    S: Sync,
+    F: Send + Sync + Fn(&S, usize) -> Array::Item,
+    Array::Item: Send,
+{
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    init_in_place(&mut ret, |i| initializer(&seed, i), &span);
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize an array given a function from index to element, checking that every element is distinct.
+///
+/// After the fill, elements are checked for duplicates in parallel using a sharded concurrent set. If several elements are duplicated any one of them may be reported.
+pub fn par_array_init_unique<Array, F>(initializer: F) -> Result<Array, DuplicateError<Array::Item>>
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send + Sync + Eq + std::hash::Hash,
+{
+    use std::collections::HashMap;
+    use std::hash::{BuildHasher, RandomState};
+    use std::sync::Mutex;
+
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    init_in_place(&mut ret, initializer, &span);
+    // Kept as a plain array until a duplicate is found, so it's dropped if Hash or Eq panics
+    let mut array = unsafe { ret.assume_init() };
+
+    let hasher = RandomState::new();
+    let shards: Vec<Mutex<HashMap<&Array::Item, usize>>> = (0..rayon::current_num_threads() * 4)
+        .map(|_| Mutex::new(HashMap::new()))
+        .collect();
+    let elems = array.mut_slice();
+    let check = |(i, elem)| {
+        let shard = hasher.hash_one(elem) as usize % shards.len();
+        let mut seen = shards[shard]
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        seen.insert(elem, i)
+            .map(|first| (first.min(i), first.max(i)))
+    };
+    let duplicate = if SEQUENTIAL {
+        elems.iter().enumerate().find_map(check)
+    } else {
+        elems.par_iter().enumerate().find_map_any(check)
+    };
+    drop(shards);
+    match duplicate {
+        None => Ok(array),
+        Some((first, second)) => {
+            let mut array = std::mem::ManuallyDrop::new(array);
+            let elems = array.mut_slice();
+            // Move the duplicate out and drop everything else in place
+            let value = unsafe { std::ptr::read(&elems[second]) };
+            unsafe {
+                std::ptr::drop_in_place(&mut elems[..second]);
+                std::ptr::drop_in_place(&mut elems[second + 1..]);
+            }
+            Err(DuplicateError {
+                value,
+                indices: (first, second),
+            })
+        }
+    }
+}
+
+/// Error returned by `par_array_init_unique` when two elements are equal.
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DuplicateError<T> {
+    /// The duplicated element, taken from the later of the two indices.
+    pub value: T,
+    /// Indices of two equal elements, in ascending order.
+    pub indices: (usize, usize),
+}
+
+impl<T: std::fmt::Debug> std::fmt::Display for DuplicateError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "elements at indices {} and {} are both {:?}",
+            self.indices.0, self.indices.1, self.value
+        )
+    }
+}
+
+impl<T: std::fmt::Debug> std::error::Error for DuplicateError<T> {}
+
+/// Initialize an array on the stack given a function from index to element, failing to compile if the array is larger than `MAX_STACK_BYTES`.
+///
+/// Large arrays returned by value can overflow the stack, especially on threads with small stacks. Arrays that are too large should be built boxed instead, with `par_array_init::<Box<[T; N]>, _>`.
+#[must_use]
+pub fn par_stack_array_init<T, F, const N: usize>(initializer: F) -> [T; N]
+where
+    F: Send + Sync + Fn(usize) -> T,
+    T: Send,
+{
+    const {
+        assert!(
+            size_of::<[T; N]>() <= MAX_STACK_BYTES,
+            "array is larger than MAX_STACK_BYTES, use par_array_init::<Box<[T; N]>, _> instead"
+        )
+    };
+    par_array_init(initializer)
+}
+
+/// Initialize an array given a function from index to element and hand a reference to it to `consume`, returning what `consume` returns.
+///
+/// The array is filled in place in this function's stack frame and never moved, which saves copying very large arrays out as a return value. It's dropped once `consume` returns, or if `consume` panics.
+pub fn par_array_init_with<Array, F, C, R>(initializer: F, consume: C) -> R
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    C: FnOnce(&Array) -> R,
+    Array::Item: Send,
+{
+    struct Filled<'a, Array>(&'a mut MaybeUninit<Array>);
+    impl<Array> Drop for Filled<'_, Array> {
+        fn drop(&mut self) {
+            // Only constructed once the array is initialized
+            unsafe { self.0.assume_init_drop() }
+        }
+    }
+
+    let span = FillSpan::new(Array::len());
+    let mut slot = MaybeUninit::<Array>::uninit();
+    init_in_place(&mut slot, initializer, &span);
+    let filled = Filled(&mut slot);
+    consume(unsafe { filled.0.assume_init_ref() })
+}
+
+/// Initialize a non-empty array given a function from index to element, failing to compile if `N` is 0.
+///
+/// For APIs that need at least one element, like taking the first element without an `Option`.
+#[must_use]
+pub fn par_array_init_non_empty<T, F, const N: usize>(initializer: F) -> [T; N]
+where
+    F: Send + Sync + Fn(usize) -> T,
+    T: Send,
+{
+    const { assert!(N > 0, "par_array_init_non_empty requires a non-empty array") };
+    par_array_init(initializer)
+}
+
+/// A type that carries an array length in an associated const, for frameworks that drive the length generically.
+///
+/// `Array` is expected to be `[Item; LEN]`, which implementors can spell out since their `LEN` is concrete, e.g. `type Array = [u8; Self::LEN]`.
+pub trait HasArrayLen {
+    /// Length of `Array`.
+    const LEN: usize;
+    /// The array type, `LEN` elements long.
+    type Array: IsParArray;
+}
+
+/// Initialize the array type of `S` given a function from index to element.
+///
+/// # Panics
+///
+/// Panics if `S::Array` doesn't have `S::LEN` elements.
+#[must_use]
+pub fn par_array_init_for<S, F>(initializer: F) -> S::Array
+where
+    S: HasArrayLen,
+    F: Send + Sync + Fn(usize) -> <S::Array as IsParArray>::Item,
+    <S::Array as IsParArray>::Item: Send,
+{
+    assert_eq!(
+        S::Array::len(),
+        S::LEN,
+        "par_array_init_for: HasArrayLen::Array doesn't have LEN elements"
+    );
+    let span = FillSpan::new(S::LEN);
+    let mut ret = MaybeUninit::<S::Array>::uninit();
+    init_in_place(&mut ret, initializer, &span);
+    // Every slot was written
+    unsafe { ret.assume_init() }
+}
+
+/// An enum whose variants can be numbered `0..COUNT`, for building lookup tables indexed by variant.
+pub trait DiscriminantIndexed: Sized {
+    /// Number of variants.
+    const COUNT: usize;
+    /// The variant numbered `index`, for every `index` below `COUNT`.
+    fn from_index(index: usize) -> Self;
+}
+
+/// Initialize a lookup table with one element per variant of `E`, given a function from variant to element.
+///
+/// Element `i` of the table is `initializer(E::from_index(i))`. Fails to compile if `N` isn't `E::COUNT`.
+#[must_use]
+pub fn par_array_init_by_variant<E, T, F, const N: usize>(initializer: F) -> [T; N]
+where
+    E: DiscriminantIndexed,
+    F: Send + Sync + Fn(E) -> T,
+    T: Send,
+{
+    const { assert!(N == E::COUNT, "table length must be the number of variants") };
+    par_array_init(|i| initializer(E::from_index(i)))
+}
+
+/// Largest array in bytes `par_stack_array_init` will build on the stack.
+///
+/// Defaults to 1MiB, and can be changed at compile time by setting the `PAR_ARRAY_INIT_MAX_STACK_BYTES` environment variable.
+pub const MAX_STACK_BYTES: usize = match option_env!("PAR_ARRAY_INIT_MAX_STACK_BYTES") {
+    Some(bytes) => parse_usize(bytes),
+    None => 1 << 20,
+};
+
+const fn parse_usize(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    assert!(!bytes.is_empty(), "PAR_ARRAY_INIT_MAX_STACK_BYTES is empty");
+    let mut value = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        assert!(
+            bytes[i].is_ascii_digit(),
+            "PAR_ARRAY_INIT_MAX_STACK_BYTES must be a number of bytes"
+        );
+        value = value * 10 + (bytes[i] - b'0') as usize;
+        i += 1;
+    }
+    value
+}
+
+/// Initialize an array given a function from index to element, with the same signature as `std::array::from_fn`.
+///
+/// Drop in replacement for `std::array::from_fn` where `cb` is called in parallel, so it has to be `Fn + Send + Sync` rather than `FnMut`.
+#[must_use]
+pub fn from_fn<T, const N: usize, F>(cb: F) -> [T; N]
+where
+    F: Send + Sync + Fn(usize) -> T,
+    T: Send,
+{
+    par_array_init(cb)
+}
+
+/// Initialize an array given a function from a seeded random number generator and index to element.
+///
+/// Requires the `rand` feature. Each index gets its own `StdRng` seeded from `master_seed` and the index, so the resulting array only depends on `master_seed` and not on how the work was scheduled across threads.
+#[cfg(feature = "rand")]
+#[must_use]
+pub fn par_array_init_rng<Array, F>(master_seed: u64, initializer: F) -> Array
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(&mut rand::rngs::StdRng, usize) -> Array::Item,
+    Array::Item: Send,
+{
+    use rand::SeedableRng;
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::uninit();
+    init_in_place(
+        &mut ret,
+        |i| {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(index_seed(master_seed, i));
+            initializer(&mut rng, i)
+        },
+        &span,
+    );
+    unsafe { ret.assume_init() }
+}
+
+/// Mix an index into a seed so neighbouring indices get unrelated seeds (splitmix64 finalizer).
+#[cfg(feature = "rand")]
+fn index_seed(master_seed: u64, i: usize) -> u64 {
+    let mut z = master_seed.wrapping_add((i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Initialize an array given a function from a counter-based random number generator and index to element.
+///
+/// Requires the `rand` feature. Unlike `par_array_init_rng`, which seeds a fresh stream per index, every number is computed directly from `(master_seed, index, position in the stream)` by the Philox4x32-10 block function. Nothing is derived from a shared stream or a hashed seed, so the array is bit-identical for any schedule and thread count, and on any platform.
+#[cfg(feature = "rand")]
+#[must_use]
+pub fn par_array_init_cbrng<Array, F>(master_seed: u64, initializer: F) -> Array
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(&mut CounterRng, usize) -> Array::Item,
+    Array::Item: Send,
+{
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::uninit();
+    init_in_place(
+        &mut ret,
+        |i| initializer(&mut CounterRng::new(master_seed, i as u64), i),
+        &span,
+    );
+    unsafe { ret.assume_init() }
+}
+
+/// Philox4x32-10 counter-based random number generator for a single index, used by `par_array_init_cbrng`.
+///
+/// Block `b` of the stream for `index` is the Philox4x32-10 function of the counter `[index, b]` (as 64 bit halves) under the key `seed`, giving four `u32`s that are handed out in order.
+#[cfg(feature = "rand")]
+#[derive(Debug, Clone)]
+// A Copy rng would silently repeat its numbers when passed by value
+#[allow(missing_copy_implementations)]
+pub struct CounterRng {
+    key: [u32; 2],
+    index: u64,
+    block: u64,
+    buffer: [u32; 4],
+    // Next unused word of buffer, 4 when it's used up
+    used: usize,
+}
+
+#[cfg(feature = "rand")]
+impl CounterRng {
+    /// Generator for the stream of `index` under `seed`.
+    pub fn new(seed: u64, index: u64) -> Self {
+        CounterRng {
+            key: [seed as u32, (seed >> 32) as u32],
+            index,
+            block: 0,
+            buffer: [0; 4],
+            used: 4,
+        }
+    }
+
+    /// The Philox4x32-10 block function.
+    fn philox(counter: [u32; 4], key: [u32; 2]) -> [u32; 4] {
+        const M0: u64 = 0xD251_1F53;
+        const M1: u64 = 0xCD9E_8D57;
+        const W0: u32 = 0x9E37_79B9;
+        const W1: u32 = 0xBB67_AE85;
+
+        let (mut c, mut k) = (counter, key);
+        for round in 0..10 {
+            if round > 0 {
+                k = [k[0].wrapping_add(W0), k[1].wrapping_add(W1)];
+            }
+            let p0 = M0 * u64::from(c[0]);
+            let p1 = M1 * u64::from(c[2]);
+            c = [
+                (p1 >> 32) as u32 ^ c[1] ^ k[0],
+                p1 as u32,
+                (p0 >> 32) as u32 ^ c[3] ^ k[1],
+                p0 as u32,
+            ];
+        }
+        c
+    }
+}
+
+#[cfg(feature = "rand")]
+impl rand::RngCore for CounterRng {
+    fn next_u32(&mut self) -> u32 {
+        if self.used == 4 {
+            let counter = [
+                self.index as u32,
+                (self.index >> 32) as u32,
+                self.block as u32,
+                (self.block >> 32) as u32,
+            ];
+            self.buffer = CounterRng::philox(counter, self.key);
+            self.block = self.block.wrapping_add(1);
+            self.used = 0;
+        }
+        self.used += 1;
+        self.buffer[self.used - 1]
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let lo = u64::from(self.next_u32());
+        lo | (u64::from(self.next_u32()) << 32)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = self.next_u32().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Initialize an array where each element is its own index, `[0, 1, 2, ..., N - 1]`.
+///
+/// Shorthand for `par_array_init(|i| i)`.
+#[must_use]
+pub fn par_array_indices<const N: usize>() -> [usize; N] {
+    par_array_init(|i| i)
+}
+
+/// Build an array of lazily evaluated cells given a function from index to element, without calling it yet.
+///
+/// Only the cells are built in parallel, each holding its own clone of `initializer` along with its index. Cell `i` calls `initializer(i)` the first time it's dereferenced and keeps the result, so elements that are never looked at are never computed. The cells are `LazyLock`s, so the array can be shared and forced from several threads.
+#[must_use]
+pub fn par_array_init_lazy<const N: usize, T, F>(
+    initializer: F,
+) -> [std::sync::LazyLock<T, impl FnOnce() -> T + Send>; N]
+where
+    T: Send,
+    F: Send + Sync + Clone + Fn(usize) -> T,
+{
+    par_array_init(|i| {
+        let initializer = initializer.clone();
+        std::sync::LazyLock::new(move || initializer(i))
+    })
+}
+
+/// Initialize an array of `Cell`s given a function from index to each cell's starting value.
+///
+/// `Cell` isn't `Sync`, but each cell is only ever touched by the task that builds it and then moved into its slot, so only `T: Send` is needed. Like any other array of cells, the finished array can't be shared between threads.
+#[must_use]
+pub fn par_array_init_cells<T, F, const N: usize>(initializer: F) -> [std::cell::Cell<T>; N]
+where
+    T: Send,
+    F: Send + Sync + Fn(usize) -> T,
+{
+    par_array_init(|i| std::cell::Cell::new(initializer(i)))
+}
+
+/// Initialize an array of durations given a function from index to duration, e.g. for schedules.
+///
+/// Shorthand for `par_array_init` with the element type fixed to `Duration`.
+#[must_use]
+pub fn par_array_durations<F, const N: usize>(initializer: F) -> [std::time::Duration; N]
+where
+    F: Send + Sync + Fn(usize) -> std::time::Duration,
+{
+    par_array_init(initializer)
+}
+
+/// Turn an array of results, like one built by `par_array_init` with a closure returning `Result`, into a result of an array.
+///
+/// Returns the first error by index. The `Ok` elements before it and every element after it are dropped. To get a `Result<[T; N], E>` straight away without keeping per-slot errors, use `try_par_array_init` instead, which also stops starting new calls after an error.
+pub fn transpose_results<T, E, const N: usize>(array: [Result<T, E>; N]) -> Result<[T; N], E> {
+    let mut ret = MaybeUninit::<[T; N]>::uninit();
+    // Owns the Ok elements moved out so far, dropping them if an error turns up
+    let mut written = Written::new(uninit_slots(&mut ret).as_mut_ptr() as *mut T);
+    for (i, result) in IntoIterator::into_iter(array).enumerate() {
+        written.push(i, result?);
+    }
+    std::mem::forget(written);
+    // Every slot was written
+    Ok(unsafe { ret.assume_init() })
+}
+
+/// Lazily produce the elements of an `N` element array given a function from index to element, as an indexed parallel iterator.
+///
+/// Equivalent to `(0..N).into_par_iter().map(initializer)`, for when the elements are only going to be processed further in parallel. The length stays part of the type, so `ParArrayIter::collect_array` can finish into `[T; N]` without an `Option`.
+pub fn par_array_init_iter<T, F, const N: usize>(initializer: F) -> ParArrayIter<F, N>
+where
+    T: Send,
+    F: Send + Sync + Fn(usize) -> T,
+{
+    ParArrayIter {
+        inner: (0..N).into_par_iter().map(initializer),
+    }
+}
+
+/// Indexed parallel iterator over the `N` elements of an array that hasn't been built yet, returned by `par_array_init_iter`.
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct ParArrayIter<F, const N: usize> {
+    inner: rayon::iter::Map<rayon::range::Iter<usize>, F>,
+}
+
+impl<F, const N: usize> std::fmt::Debug for ParArrayIter<F, N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParArrayIter").field("len", &N).finish()
+    }
+}
+
+impl<F, const N: usize> ParArrayIter<F, N> {
+    /// Collect the elements into an array.
+    #[must_use]
+    pub fn collect_array<T>(self) -> [T; N]
+    where
+        T: Send,
+        F: Send + Sync + Fn(usize) -> T,
+    {
+        let span = FillSpan::new(N);
+        let mut ret = MaybeUninit::<[T; N]>::uninit();
+        fill_par_iter(uninit_slots(&mut ret), self, &span);
+        // The iterator has exactly N elements
+        unsafe { ret.assume_init() }
+    }
+}
+
+impl<T, F, const N: usize> ParallelIterator for ParArrayIter<F, N>
+where
+    T: Send,
+    F: Send + Sync + Fn(usize) -> T,
+{
+    type Item = T;
+
+    fn drive_unindexed<C: UnindexedConsumer<T>>(self, consumer: C) -> C::Result {
+        self.inner.drive_unindexed(consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(N)
+    }
+}
+
+impl<T, F, const N: usize> IndexedParallelIterator for ParArrayIter<F, N>
+where
+    T: Send,
+    F: Send + Sync + Fn(usize) -> T,
+{
+    fn drive<C: Consumer<T>>(self, consumer: C) -> C::Result {
+        self.inner.drive(consumer)
+    }
+
+    fn len(&self) -> usize {
+        N
+    }
+
+    fn with_producer<CB: ProducerCallback<T>>(self, callback: CB) -> CB::Output {
+        self.inner.with_producer(callback)
+    }
+}
+
+/// Initialize an array given a parallel iterator.
+///
+/// IndexedParallelIterator is required (as opposed to just ParallelIterator) so we know we have atleast as many elements as array length.
+///
+/// Takes elements from the iterator until the Array is full, returns full array on completion. Returns None if there are not enough elements.
+#[must_use]
+pub fn from_par_iter<Array, I>(into_iter: I) -> Option<Array>
+where
+    I: IntoParallelIterator<Item = Array::Item>,
+    I::Iter: IndexedParallelIterator<Item = Array::Item>,
+    Array: IsParArray,
+    Array::Item: Send,
+{
+    let iter = into_iter.into_par_iter();
+    if Array::len() > iter.len() {
+        return None;
+    }
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    fill_par_iter(uninit_slots(&mut ret), iter, &span);
+    // Every slot was written since iter has atleast Array::len() elements
+    Some(unsafe { ret.assume_init() })
+}
+
+/// Initialize an array from a map whose keys are exactly the indices `0..Array::len()`, moving each value into its key's slot.
+///
+/// Returns `None`, dropping the map, if any index is missing or there are keys past the end of the array. A `BTreeMap` can only be walked in order, so the values are moved one after another on the calling thread; there's nothing to compute in parallel.
+#[must_use]
+pub fn from_btreemap<Array>(map: std::collections::BTreeMap<usize, Array::Item>) -> Option<Array>
+where
+    Array: IsParArray,
+{
+    // Keys are unique and sorted, so they're dense exactly when there are len of them ending at len - 1
+    let dense =
+        map.len() == Array::len() && map.keys().next_back().copied() == Array::len().checked_sub(1);
+    if !dense {
+        return None;
+    }
+    let _span = FillSpan::with_threads(Array::len(), 1);
+    let mut ret = MaybeUninit::<Array>::uninit();
+    let slots = uninit_slots(&mut ret);
+    for (slot, value) in slots.iter_mut().zip(map.into_values()) {
+        slot.write(value);
+    }
+    // Every slot was written since there were exactly len values
+    Some(unsafe { ret.assume_init() })
+}
+
+/// Initialize an array given a parallel iterator, reporting the iterator's length if it's too short.
+///
+/// Behaves like `from_par_iter`, but on failure returns `Err` with the number of elements the iterator actually had.
+pub fn from_par_iter_len<Array, I>(into_iter: I) -> Result<Array, usize>
+where
+    I: IntoParallelIterator<Item = Array::Item>,
+    I::Iter: IndexedParallelIterator<Item = Array::Item>,
+    Array: IsParArray,
+    Array::Item: Send,
+{
+    let iter = into_iter.into_par_iter();
+    let len = iter.len();
+    from_par_iter(iter).ok_or(len)
+}
+
+/// Initialize an array given a parallel iterator that has to have exactly `Array::len()` elements.
+///
+/// Unlike `from_par_iter` a too long iterator isn't truncated. If the iterator has any other number of elements than the array, all of them are collected in order and returned as the `Err`, so nothing is lost. Compare its length against `Array::len()` to tell a too long iterator from a too short one.
+pub fn from_par_iter_strict<Array, I>(into_iter: I) -> Result<Array, Vec<Array::Item>>
+where
+    I: IntoParallelIterator<Item = Array::Item>,
+    I::Iter: IndexedParallelIterator<Item = Array::Item>,
+    Array: IsParArray,
+    Array::Item: Send,
+{
+    let iter = into_iter.into_par_iter();
+    if iter.len() != Array::len() {
+        return Err(iter.collect());
+    }
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    fill_par_iter(uninit_slots(&mut ret), iter, &span);
+    // Every slot was written since iter has exactly Array::len() elements
+    Ok(unsafe { ret.assume_init() })
+}
+
+/// Initialize an array given a parallel iterator that has to have exactly `Array::len()` elements, panicking if it doesn't.
+///
+/// For callers that treat a length mismatch as a bug. Use `from_par_iter` or `from_par_iter_strict` when a mismatch is expected and should be handled.
+///
+/// # Panics
+/// Panics, reporting both lengths at the caller's location, if `iter.len()` isn't `Array::len()`.
+#[must_use]
+#[track_caller]
+pub fn from_par_iter_or_panic<Array, I>(into_iter: I) -> Array
+where
+    I: IntoParallelIterator<Item = Array::Item>,
+    I::Iter: IndexedParallelIterator<Item = Array::Item>,
+    Array: IsParArray,
+    Array::Item: Send,
+{
+    let iter = into_iter.into_par_iter();
+    assert_eq!(
+        iter.len(),
+        Array::len(),
+        "from_par_iter_or_panic: iterator has a different length than the array"
+    );
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    fill_par_iter(uninit_slots(&mut ret), iter, &span);
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize a struct of arrays from two parallel iterators of the same length, writing element `i` of each into its own array in one pass.
+///
+/// For struct-of-arrays layouts. Both iterators are zipped and walked once, which saves splitting and scheduling two separate `from_par_iter` fills. Returns None if either iterator doesn't have exactly `N` elements.
+#[must_use]
+pub fn par_array_init_soa<const N: usize, A, B, IA, IB>(ia: IA, ib: IB) -> Option<([A; N], [B; N])>
+where
+    A: Send,
+    B: Send,
+    IA: IntoParallelIterator<Item = A>,
+    IA::Iter: IndexedParallelIterator<Item = A>,
+    IB: IntoParallelIterator<Item = B>,
+    IB::Iter: IndexedParallelIterator<Item = B>,
+{
+    let (ia, ib) = (ia.into_par_iter(), ib.into_par_iter());
+    if ia.len() != N || ib.len() != N {
+        return None;
+    }
+    let span = FillSpan::new(N);
+    let mut a = MaybeUninit::<[A; N]>::uninit();
+    let mut b = MaybeUninit::<[B; N]>::uninit();
+    let a_origin = Written::new(a.as_mut_ptr() as *mut A);
+    let b_origin = Written::new(b.as_mut_ptr() as *mut B);
+    let indexed = (0..N)
+        .into_par_iter()
+        .zip(ia.zip(ib))
+        .with_min_len(default_min_len::<(A, B)>(N));
+    if SEQUENTIAL {
+        let (mut a_written, mut b_written) = (a_origin.empty(), b_origin.empty());
+        for_each_in_order(indexed, |(i, (a, b))| {
+            a_written.push(i, a);
+            b_written.push(i, b);
+        });
+        span.chunk(0, N);
+        // As below, both runs cover every slot
+        std::mem::forget(a_written);
+        std::mem::forget(b_written);
+        return Some(unsafe { (a.assume_init(), b.assume_init()) });
+    }
+    let (a_written, b_written) = indexed
+        .fold(
+            || (a_origin.empty(), b_origin.empty()),
+            |(mut a_written, mut b_written), (i, (a, b))| {
+                a_written.push(i, a);
+                b_written.push(i, b);
+                (a_written, b_written)
+            },
+        )
+        .inspect(|(written, _)| span.chunk(written.start, written.len))
+        .reduce(
+            || (a_origin.empty(), b_origin.empty()),
+            |(a_left, b_left), (a_right, b_right)| (a_left.merge(a_right), b_left.merge(b_right)),
+        );
+    // Both iterators had exactly N elements, so both runs cover every slot
+    std::mem::forget(a_written);
+    std::mem::forget(b_written);
+    Some(unsafe { (a.assume_init(), b.assume_init()) })
+}
+
+/// Initialize an array given two parallel iterators, filling the first half of the array from `first` and the second half from `second`.
+///
+/// Both halves are filled at the same time. Like `from_par_iter`, each iterator has to have atleast `Array::len() / 2` elements. Returns None if either is too short, or if the array has an odd length and can't be split into halves.
+#[must_use]
+pub fn from_par_iters_halves<Array, I1, I2>(first: I1, second: I2) -> Option<Array>
+where
+    I1: IntoParallelIterator<Item = Array::Item>,
+    I1::Iter: IndexedParallelIterator<Item = Array::Item>,
+    I2: IntoParallelIterator<Item = Array::Item>,
+    I2::Iter: IndexedParallelIterator<Item = Array::Item>,
+    Array: IsParArray,
+    Array::Item: Send,
+{
+    let (first, second) = (first.into_par_iter(), second.into_par_iter());
+    let len = Array::len();
+    let half = len / 2;
+    if len % 2 != 0 || half > first.len() || half > second.len() {
+        return None;
+    }
+    let _span = FillSpan::new(len);
+    let mut ret = MaybeUninit::<Array>::uninit();
+    let (head, tail) = uninit_slots(&mut ret).split_at_mut(half);
+    // If either half panics, join waits for the other and drops what it wrote before resuming the panic
+    // Both halves are part of this fill's span
+    let fill_head = || {
+        let indexed = (0..half).into_par_iter().zip(first);
+        write_indexed(head, indexed, |elem| elem, &FillSpan::nested())
+    };
+    let fill_tail = || {
+        let indexed = (0..half).into_par_iter().zip(second);
+        write_indexed(tail, indexed, |elem| elem, &FillSpan::nested())
+    };
+    let (head, tail) = if SEQUENTIAL {
+        // If the tail panics the head's run is dropped as this unwinds
+        let head = fill_head();
+        (head, fill_tail())
+    } else {
+        rayon::join(fill_head, fill_tail)
+    };
+    if head.len != half || tail.len != half {
+        unreachable!(
+            "par_array_init: iterators promised atleast {} elements but only produced {} and {}",
+            half, head.len, tail.len
+        );
+    }
+    std::mem::forget(head);
+    std::mem::forget(tail);
+    Some(unsafe { ret.assume_init() })
+}
+
+/// Fill an `ArrayVec` from a parallel iterator that may have fewer elements than its capacity.
+///
+/// Requires the `arrayvec` feature. Takes up to `N` elements from the iterator, filling them in parallel, and returns an `ArrayVec` holding however many there were.
+#[cfg(feature = "arrayvec")]
+#[must_use]
+pub fn from_par_iter_arrayvec<const N: usize, T, I>(into_iter: I) -> arrayvec::ArrayVec<T, N>
+where
+    I: IntoParallelIterator<Item = T>,
+    I::Iter: IndexedParallelIterator<Item = T>,
+    T: Send,
+{
+    let iter = into_iter.into_par_iter();
+    let len = iter.len().min(N);
+    let span = FillSpan::new(len);
+    let mut ret = arrayvec::ArrayVec::new();
+    // An empty ArrayVec has room for N uninitialized elements
+    let slots =
+        unsafe { std::slice::from_raw_parts_mut(ret.as_mut_ptr() as *mut MaybeUninit<T>, len) };
+    fill_par_iter(slots, iter.take(len), &span);
+    // The first len elements were all written
+    unsafe { ret.set_len(len) };
+    ret
+}
+
+/// Initialize a 2D array given a flat parallel iterator, filling it in row-major order.
+///
+/// Like `from_par_iter`, this takes elements from the iterator until the array is full and returns None if there are fewer than `R * C` elements.
+#[must_use]
+pub fn from_par_iter_2d<const R: usize, const C: usize, T, I>(into_iter: I) -> Option<[[T; C]; R]>
+where
+    I: IntoParallelIterator<Item = T>,
+    I::Iter: IndexedParallelIterator<Item = T>,
+    T: Send,
+{
+    let iter = into_iter.into_par_iter();
+    let len = R.checked_mul(C)?;
+    if len > iter.len() {
+        return None;
+    }
+    let span = FillSpan::new(len);
+    let mut ret = MaybeUninit::<[[T; C]; R]>::uninit();
+    // Nested arrays have no padding, so [[T; C]; R] is laid out as R * C contiguous T's
+    let slots =
+        unsafe { std::slice::from_raw_parts_mut(ret.as_mut_ptr() as *mut MaybeUninit<T>, len) };
+    fill_par_iter(slots, iter, &span);
+    Some(unsafe { ret.assume_init() })
+}
+
+/// Initialize a symmetric `N` by `N` matrix given a function from `(i, j)` indices to element, only calling it for the upper triangle.
+///
+/// `initializer(i, j)` is called once for each `i <= j`, in parallel, and its result is cloned into both `[i][j]` and `[j][i]`. That halves the work for symmetric relations like distances.
+#[must_use]
+pub fn par_array_init_symmetric<const N: usize, T, F>(initializer: F) -> [[T; N]; N]
+where
+    F: Send + Sync + Fn(usize, usize) -> T,
+    T: Send + Sync + Clone,
+{
+    let span = FillSpan::new(N);
+    // Row i of the upper triangle holds columns i..N, computing it is part of this fill's span
+    let upper_row = |i| (i..N).map(|j| initializer(i, j)).collect::<Vec<_>>();
+    let upper = vec_init(N, upper_row, &FillSpan::nested());
+    let mut ret = MaybeUninit::<[[T; N]; N]>::uninit();
+    init_in_place(
+        &mut ret,
+        |i| {
+            std::array::from_fn(|j| {
+                let (row, col) = if i <= j { (i, j) } else { (j, i) };
+                upper[row][col - row].clone()
+            })
+        },
+        &span,
+    );
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize an array of fixed size blocks given a function from `(block, offset)` indices to element.
+///
+/// For tiling flat data like image rows into blocks, `initializer(b, o)` gives element `o` of block `b`, the element at flat index `b * BLOCK_LEN + o`. The blocks are filled as one flat run of `BLOCKS * BLOCK_LEN` elements in parallel. Fails to compile if `BLOCKS * BLOCK_LEN` overflows `usize`, which is only possible for zero sized `T`.
+#[must_use]
+pub fn par_array_init_tiled<const BLOCKS: usize, const BLOCK_LEN: usize, T, F>(
+    initializer: F,
+) -> [[T; BLOCK_LEN]; BLOCKS]
+where
+    F: Send + Sync + Fn(usize, usize) -> T,
+    T: Send,
+{
+    const {
+        assert!(
+            BLOCKS.checked_mul(BLOCK_LEN).is_some(),
+            "BLOCKS * BLOCK_LEN overflows usize"
+        )
+    };
+    let len = BLOCKS * BLOCK_LEN;
+    let span = FillSpan::new(len);
+    let mut ret = MaybeUninit::<[[T; BLOCK_LEN]; BLOCKS]>::uninit();
+    // Nested arrays have no padding, so [[T; BLOCK_LEN]; BLOCKS] is laid out as BLOCKS * BLOCK_LEN contiguous T's
+    let slots =
+        unsafe { std::slice::from_raw_parts_mut(ret.as_mut_ptr() as *mut MaybeUninit<T>, len) };
+    // len is only 0 when BLOCK_LEN is, in which case initializer is never called
+    fill_par_fn(
+        slots,
+        default_min_len::<T>(len),
+        |i| initializer(i / BLOCK_LEN, i % BLOCK_LEN),
+        &span,
+    );
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize a 3D array given a function from `(w, h, d)` indices to element.
+///
+/// The nested array is filled as one flat run of `W * H * D` elements in parallel. Fails to compile if `W * H * D` overflows `usize`, which is only possible for zero sized `T`.
+#[must_use]
+pub fn par_array_init_3d<const W: usize, const H: usize, const D: usize, T, F>(
+    initializer: F,
+) -> [[[T; D]; H]; W]
+where
+    F: Send + Sync + Fn(usize, usize, usize) -> T,
+    T: Send,
+{
+    const {
+        assert!(
+            match W.checked_mul(H) {
+                Some(wh) => wh.checked_mul(D).is_some(),
+                None => false,
+            },
+            "W * H * D overflows usize"
+        )
+    };
+    let len = W * H * D;
+    let span = FillSpan::new(len);
+    let mut ret = MaybeUninit::<[[[T; D]; H]; W]>::uninit();
+    // Nested arrays have no padding, so [[[T; D]; H]; W] is laid out as W * H * D contiguous T's
+    let slots =
+        unsafe { std::slice::from_raw_parts_mut(ret.as_mut_ptr() as *mut MaybeUninit<T>, len) };
+    // len is only 0 when H or D is, in which case initializer is never called
+    fill_par_fn(
+        slots,
+        default_min_len::<T>(len),
+        |i| initializer(i / (H * D), i / D % H, i % D),
+        &span,
+    );
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize an array from an async stream.
+///
+/// Requires the `async` feature. The stream is collected into a buffer first, then the array is filled from the buffer in parallel on the rayon thread pool so the async executor isn't blocked. Resolves to None if the stream has fewer elements than the array.
+#[cfg(feature = "async")]
+pub async fn from_stream<Array, S>(stream: S) -> Option<Array>
+where
+    S: futures::Stream<Item = Array::Item>,
+    Array: IsParArray + Send + 'static,
+    Array::Item: Send + 'static,
+{
+    use futures::StreamExt;
+
+    let buffer: Vec<Array::Item> = stream.take(Array::len()).collect().await;
+    if buffer.len() < Array::len() {
+        return None;
+    }
+    if SEQUENTIAL {
+        return from_par_iter(buffer);
+    }
+    let (send, recv) = futures::channel::oneshot::channel();
+    rayon::spawn(move || {
+        // The receiver only goes away if the future was dropped, in which case no one wants the array
+        let _ = send.send(from_par_iter(buffer));
+    });
+    recv.await.ok().flatten()
+}
+
+/// Initialize an array on the rayon pool in the background given a function from index to element, delivering it through a oneshot channel.
+///
+/// Requires the `async` feature. The returned receiver is a future that resolves to the array, so callers can `select!` it against a timeout. Dropping the receiver cancels the fill: indices after the first one to notice are no longer started, and the elements written so far are dropped.
+/// The fill runs under `rayon::spawn`, so a panic in `initializer` goes to the pool's panic handler, which aborts by default. If the handler doesn't abort, the receiver resolves to `Err(Canceled)`.
+#[cfg(feature = "async")]
+pub fn par_array_init_channel<Array, F>(
+    initializer: F,
+) -> futures::channel::oneshot::Receiver<Array>
+where
+    Array: IsParArray + Send + 'static,
+    F: Send + Sync + 'static + Fn(usize) -> Array::Item,
+    Array::Item: Send,
+{
+    let (send, recv) = futures::channel::oneshot::channel();
+    let fill = move || {
+        let array = try_par_array_init(|i| {
+            if send.is_canceled() {
+                Err(())
+            } else {
+                Ok(initializer(i))
+            }
+        });
+        if let Ok(array) = array {
+            // The receiver only goes away if the caller cancelled, in which case no one wants the array
+            let _ = send.send(array);
+        }
+    };
+    if SEQUENTIAL {
+        // There's no pool to hand the fill to, so it's finished before the receiver is returned
+        fill();
+    } else {
+        rayon::spawn(fill);
+    }
+    recv
+}
+
+/// Initialize an array given an async function from index to element, driving the futures to completion on the rayon pool.
+///
+/// Requires the `async` feature. The array is cut into chunks like `par_array_init_stable_order`, and each chunk is one rayon task that `block_on`s a future awaiting its elements' futures one after another, left to right.
+///
+/// This is for closures that do a little async work, like awaiting an already filled cache. There's no async runtime behind it:
+/// - a pending future blocks the worker thread until it's woken, so futures that wait on I/O or timers hold up the pool,
+/// - futures that need a particular runtime's reactor, like tokio's, have to be run inside that runtime instead,
+/// - futures within a chunk never run concurrently with each other.
+#[cfg(feature = "async")]
+#[must_use]
+pub fn par_array_init_async_each<Array, F, Fut>(initializer: F) -> Array
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Fut,
+    Fut: std::future::Future<Output = Array::Item>,
+    Array::Item: Send,
+{
+    let len = Array::len();
+    let chunk_len = default_min_len::<Array::Item>(len);
+    let span = FillSpan::new(len);
+    let mut ret = MaybeUninit::<Array>::uninit();
+    if SEQUENTIAL {
+        fill_in_order(
+            uninit_slots(&mut ret),
+            |i| futures::executor::block_on(initializer(i)),
+            &span,
+        );
+        return unsafe { ret.assume_init() };
+    }
+    let origin = Written::new(uninit_slots(&mut ret).as_mut_ptr() as *mut Array::Item);
+    let written = (0..len.div_ceil(chunk_len))
+        .into_par_iter()
+        .map(|chunk| {
+            let start = chunk * chunk_len;
+            let mut written = origin.empty();
+            futures::executor::block_on(async {
+                for i in start..len.min(start.saturating_add(chunk_len)) {
+                    let _permit = Permit::acquire();
+                    written.push(i, initializer(i).await);
+                }
+            });
+            span.chunk(written.start, written.len);
+            written
+        })
+        .reduce(|| origin.empty(), Written::merge);
+    // The chunks cover every slot
+    std::mem::forget(written);
+    unsafe { ret.assume_init() }
+}
+
+/// Build `N` futures given a function from index to future and await them all concurrently, resolving to their outputs in index order.
+///
+/// Requires the `async` feature. For fan-out: the futures are constructed in parallel on the rayon pool and then driven together with `futures::future::join_all` by whichever executor awaits this, so while one is pending the others make progress. Unlike `par_array_init_async_each` no rayon worker is blocked, but the futures are polled on the awaiting task, one at a time, so CPU heavy work inside them doesn't run in parallel.
+#[cfg(feature = "async")]
+pub async fn par_array_init_join_all<const N: usize, T, F, Fut>(initializer: F) -> [T; N]
+where
+    F: Send + Sync + Fn(usize) -> Fut,
+    Fut: std::future::Future<Output = T> + Send,
+{
+    let futures: [Fut; N] = par_array_init(initializer);
+    let outputs: Vec<T> = futures::future::join_all(futures).await;
+    // join_all resolves to one output per future, in order
+    std::convert::TryFrom::try_from(outputs)
+        .unwrap_or_else(|_| unreachable!("par_array_init: join_all lost a future's output"))
+}
+
+/// Initialize an array given a parallel iterator, padding any slots the iterator doesn't fill with clones of `pad`.
+///
+/// Takes elements from the iterator until the Array is full. If the iterator is too short the remaining tail of the array is filled with `pad`, so unlike `from_par_iter` this always returns a full array.
+#[must_use]
+pub fn from_par_iter_padded<Array, I>(into_iter: I, pad: Array::Item) -> Array
+where
+    I: IntoParallelIterator<Item = Array::Item>,
+    I::Iter: IndexedParallelIterator<Item = Array::Item>,
+    Array: IsParArray,
+    Array::Item: Clone + Send,
+{
+    let iter = into_iter.into_par_iter();
+    let split = iter.len().min(Array::len());
+    let padding = rayon::iter::repeat_n(pad, Array::len() - split);
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    fill_par_iter(
+        uninit_slots(&mut ret),
+        iter.take(split).chain(padding),
+        &span,
+    );
+    // The iterator prefix and the padding together cover every slot
+    unsafe { ret.assume_init() }
+}
+
+/// Map each element of an array to a new array in parallel.
+///
+/// `src` is consumed and each element is moved into `f`, so neither element type needs to be `Copy`. If `f` panics, the remaining elements of `src` and any already mapped elements are dropped.
+#[must_use]
+pub fn par_array_map<A, B, F, const N: usize>(src: [A; N], f: F) -> [B; N]
+where
+    A: Send,
+    B: Send,
+    F: Send + Sync + Fn(A) -> B,
+{
+    let span = FillSpan::new(N);
+    let mut ret = MaybeUninit::<[B; N]>::uninit();
+    fill_par_iter(uninit_slots(&mut ret), src.into_par_iter().map(f), &span);
+    // src has exactly N elements so every slot was written
+    unsafe { ret.assume_init() }
+}
+
+/// Check whether every element of an array satisfies `pred`, testing the elements in parallel.
+///
+/// Uses rayon's short circuiting `all`, so once any element fails the elements that haven't been tested yet are skipped. Handy for validating a just built array without going back to a sequential pass.
+pub fn par_all<Array, P>(array: &Array, pred: P) -> bool
+where
+    Array: IsParArray,
+    P: Send + Sync + Fn(&Array::Item) -> bool,
+    Array::Item: Sync,
+{
+    if SEQUENTIAL {
+        array.as_ffi_slice().iter().all(pred)
+    } else {
+        array.as_ffi_slice().par_iter().all(pred)
+    }
+}
+
+/// Check whether any element of an array satisfies `pred`, testing the elements in parallel.
+///
+/// Uses rayon's short circuiting `any`, so once any element matches the elements that haven't been tested yet are skipped.
+pub fn par_any<Array, P>(array: &Array, pred: P) -> bool
+where
+    Array: IsParArray,
+    P: Send + Sync + Fn(&Array::Item) -> bool,
+    Array::Item: Sync,
+{
+    if SEQUENTIAL {
+        array.as_ffi_slice().iter().any(pred)
+    } else {
+        array.as_ffi_slice().par_iter().any(pred)
+    }
+}
+
+/// Map every element of an array in parallel with a fallible function, e.g. a `TryFrom` conversion.
+///
+/// Once an element fails, elements after it are no longer started. On failure the error from the lowest failing index is returned, and the elements converted so far are dropped.
+pub fn par_array_try_map<A, B, F, E, const N: usize>(src: [A; N], f: F) -> Result<[B; N], E>
+where
+    A: Send,
+    B: Send,
+    E: Send,
+    F: Send + Sync + Fn(A) -> Result<B, E>,
+{
+    let span = FillSpan::new(N);
+    let mut ret = MaybeUninit::<[B; N]>::uninit();
+    match try_fill_par_iter(
+        uninit_slots(&mut ret),
+        src.into_par_iter(),
+        |_, elem| f(elem),
+        &span,
+    ) {
+        // Every slot was written
+        Ok(()) => Ok(unsafe { ret.assume_init() }),
+        Err(failure) => Err(failure.error),
+    }
+}
+
+/// Initialize a packed bit array given a function from bit index to bit.
+///
+/// Requires the `bitvec` feature. Bits are packed `usize::BITS` to a word, taking an eighth of the memory of a `[bool; N]`. Each word is computed by a single task, so no two threads ever write the same word.
+/// `initializer` is called for every bit of the `W` words, so for `BitArr!(for N)` it also fills the padding bits past `N` when `N` isn't a multiple of `usize::BITS`.
+#[cfg(feature = "bitvec")]
+#[must_use]
+pub fn par_bitset_init<const W: usize, F>(initializer: F) -> bitvec::array::BitArray<[usize; W]>
+where
+    F: Send + Sync + Fn(usize) -> bool,
+{
+    let bits = usize::BITS as usize;
+    let words: [usize; W] = par_array_init(|w| {
+        (0..bits).fold(0, |word, b| {
+            word | (usize::from(initializer(w * bits + b)) << b)
+        })
+    });
+    bitvec::array::BitArray::new(words)
+}
+
+/// Initialize an array given a function from index to element, substituting `fallback(i)` for every index not started before `deadline`.
+///
+/// Requires the `bitvec` feature. Calls to `initializer` that are already running are left to finish, only indices whose turn comes after the deadline take the fallback. Bit `i` of the returned bit array is set when slot `i` holds a fallback, so callers know which slots to recompute later.
+///
+/// # Panics
+///
+/// Panics if the `W` words have fewer bits than the array has elements.
+#[cfg(feature = "bitvec")]
+pub fn par_array_init_timeout_marked<Array, F, D, const W: usize>(
+    deadline: std::time::Instant,
+    initializer: F,
+    fallback: D,
+) -> (Array, bitvec::array::BitArray<[usize; W]>)
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    D: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send,
+{
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let bits = usize::BITS as usize;
+    assert!(
+        Array::len() <= W * bits,
+        "par_array_init_timeout_marked: bit array is too small for the array"
+    );
+    let marks: [AtomicUsize; W] = std::array::from_fn(|_| AtomicUsize::new(0));
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    init_in_place(
+        &mut ret,
+        |i| {
+            if std::time::Instant::now() < deadline {
+                initializer(i)
+            } else {
+                marks[i / bits].fetch_or(1 << (i % bits), Ordering::Relaxed);
+                fallback(i)
+            }
+        },
+        &span,
+    );
+    // Every slot was written
+    let array = unsafe { ret.assume_init() };
+    let words = marks.map(AtomicUsize::into_inner);
+    (array, bitvec::array::BitArray::new(words))
+}
+
+/// Initialize an array with every element set to zero.
+///
+/// Requires the `bytemuck` feature. An all zero bit pattern is a valid value for any `Zeroable` type, so rather than calling an initializer per element the whole array is zeroed with a single `write_bytes`.
+#[cfg(feature = "bytemuck")]
+#[must_use]
+pub fn par_array_zeroed<Array>() -> Array
+where
+    Array: IsParArray,
+    Array::Item: bytemuck::Zeroable,
+{
+    let _span = FillSpan::with_threads(Array::len(), 1);
+    let mut ret = MaybeUninit::<Array>::uninit();
+    unsafe {
+        ret.as_mut_ptr().write_bytes(0, 1);
+        ret.assume_init()
+    }
+}
+
+/// Combine two arrays element by element into a new array in parallel.
+///
+/// Both inputs are consumed and `f(a[i], b[i])` becomes element `i` of the result. Like `par_array_map` no element type needs to be `Copy`.
+#[must_use]
+pub fn par_array_zip_map<A, B, C, F, const N: usize>(a: [A; N], b: [B; N], f: F) -> [C; N]
+where
+    A: Send,
+    B: Send,
+    C: Send,
+    F: Send + Sync + Fn(A, B) -> C,
+{
+    let elems = a.into_par_iter().zip(b).map(|(a, b)| f(a, b));
+    let span = FillSpan::new(N);
+    let mut ret = MaybeUninit::<[C; N]>::uninit();
+    fill_par_iter(uninit_slots(&mut ret), elems, &span);
+    // a and b both have exactly N elements so every slot was written
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize an array given a function from index to element, then sort it with `compare`.
+///
+/// The sort is rayon's `par_sort_by`, done in place once the fill has finished, so it's stable and needs no second array.
+#[must_use]
+pub fn par_array_init_sorted<Array, F, C>(initializer: F, compare: C) -> Array
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    C: Sync + Fn(&Array::Item, &Array::Item) -> std::cmp::Ordering,
+    Array::Item: Send,
+{
+    use rayon::slice::ParallelSliceMut;
+
+    let span = FillSpan::new(Array::len());
+    let mut array = MaybeUninit::<Array>::uninit();
+    init_in_place(&mut array, initializer, &span);
+    // Every slot was written
+    let mut array = unsafe { array.assume_init() };
+    if SEQUENTIAL {
+        array.mut_slice().sort_by(compare);
+    } else {
+        array.mut_slice().par_sort_by(compare);
+    }
+    array
+}
+
+/// Initialize an array given a function from index to element, along with the permutation of indices that would sort it by `compare`.
+///
+/// The array itself is left in index order. `array[perm[0]], array[perm[1]], ...` is sorted, with ties kept in index order since the parallel sort is stable.
+#[must_use]
+pub fn par_array_init_argsort<T, F, C, const N: usize>(
+    initializer: F,
+    compare: C,
+) -> ([T; N], [usize; N])
+where
+    T: Send + Sync,
+    F: Send + Sync + Fn(usize) -> T,
+    C: Sync + Fn(&T, &T) -> std::cmp::Ordering,
+{
+    use rayon::slice::ParallelSliceMut;
+
+    let span = FillSpan::new(N);
+    let mut array = MaybeUninit::<[T; N]>::uninit();
+    init_in_place(&mut array, initializer, &span);
+    let array = unsafe { array.assume_init() };
+    // The permutation is part of this fill's span
+    let mut perm = MaybeUninit::<[usize; N]>::uninit();
+    init_in_place(&mut perm, |i| i, &FillSpan::nested());
+    let mut perm = unsafe { perm.assume_init() };
+    let by_element = |&a: &usize, &b: &usize| compare(&array[a], &array[b]);
+    if SEQUENTIAL {
+        perm.sort_by(by_element);
+    } else {
+        perm.par_sort_by(by_element);
+    }
+    (array, perm)
+}
+
+/// 64 bit FNV-1a, the hash behind `par_array_init_audited`'s log.
+///
+/// Every integer is widened to 64 bits (128 bit integers aside) and written little endian, so the hash of a value doesn't depend on the target's pointer width or byte order.
+struct AuditHasher(u64);
+
+impl Default for AuditHasher {
+    fn default() -> Self {
+        AuditHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl std::hash::Hasher for AuditHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.write_u64(u64::from(i));
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write_u64(u64::from(i));
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write_u64(u64::from(i));
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i8(&mut self, i: i8) {
+        self.write_i64(i64::from(i));
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write_i64(i64::from(i));
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_i64(i64::from(i));
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_i128(&mut self, i: i128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_i64(i as i64);
+    }
+}
+
+/// Initialize an array given a function from index to element, along with a hash of every element in index order.
+///
+/// The hashes are 64 bit FNV-1a, computed by this crate rather than `DefaultHasher` whose algorithm may change between Rust releases. Integers are hashed as little endian `u64`s whatever their width, so `usize` hashes the same on 32 and 64 bit targets. For a deterministic `initializer` the log is the same on every run, toolchain and target no matter how the work was split, as long as the element type's `Hash` impl feeds the hasher the same values. An auditor can replay the fill and compare logs.
+#[must_use]
+pub fn par_array_init_audited<Array, F>(initializer: F) -> (Array, Vec<u64>)
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send + Sync + std::hash::Hash,
+{
+    use std::hash::{Hash, Hasher};
+
+    let span = FillSpan::new(Array::len());
+    let mut array = MaybeUninit::<Array>::uninit();
+    init_in_place(&mut array, initializer, &span);
+    // Every slot was written
+    let array = unsafe { array.assume_init() };
+    let log = par_map_collect(array.as_ffi_slice().par_iter(), |elem| {
+        let mut hasher = AuditHasher::default();
+        elem.hash(&mut hasher);
+        hasher.finish()
+    });
+    (array, log)
+}
+
+/// Initialize an array given a fallible function from index to element.
+///
+/// Once an index fails, indices after it are no longer started, and the error from the lowest failing index is returned. Indices before a failure are always still run, so when several fail it's the same error whichever order they ran in. Any elements that were already completed are dropped.
+pub fn try_par_array_init<Array, F, E>(initializer: F) -> Result<Array, E>
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Result<Array::Item, E>,
+    Array::Item: Send,
+    E: Send,
+{
+    try_par_array_init_partial(initializer).map_err(|(_, error)| error)
+}
+
+/// Initialize an array given a function from index to element, checking each element with `validate` as soon as it's computed.
+///
+/// `validate(i, &elem)` runs in the same task right after `initializer(i)`, so a bad element stops the fill early instead of being found after it. Once an element is rejected indices after it are no longer started, and the lowest rejected index is returned. Any elements that were already completed, and the rejected ones, are dropped.
+pub fn par_array_init_validate_streaming<Array, F, V>(
+    initializer: F,
+    validate: V,
+) -> Result<Array, usize>
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    V: Send + Sync + Fn(usize, &Array::Item) -> bool,
+    Array::Item: Send,
+{
+    try_par_array_init(|i| {
+        let elem = initializer(i);
+        if validate(i, &elem) {
+            Ok(elem)
+        } else {
+            Err(i)
+        }
+    })
+}
+
+/// Initialize an array given a function from index to an optional element.
+///
+/// This is the `Option` counterpart to `try_par_array_init`: once an index yields `None`, indices after it are no longer started and `None` is returned. Any elements that were already completed are dropped.
+#[must_use]
+pub fn par_array_init_opt<Array, F>(initializer: F) -> Option<Array>
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Option<Array::Item>,
+    Array::Item: Send,
+{
+    try_par_array_init(|i| initializer(i).ok_or(())).ok()
+}
+
+/// Initialize an array given a fallible function from index to element, retrying each failing index up to `max_retries` times.
+///
+/// For closures that hit transient errors. An index only fails once `initializer` has failed `max_retries + 1` times in a row for it, and then behaves like `try_par_array_init`: later indices are no longer started and the error from the lowest index to run out of retries is returned.
+pub fn par_array_init_retry<Array, F, E>(max_retries: usize, initializer: F) -> Result<Array, E>
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Result<Array::Item, E>,
+    Array::Item: Send,
+    E: Send,
+{
+    try_par_array_init(|i| {
+        let mut retries = 0;
+        loop {
+            match initializer(i) {
+                Err(_) if retries < max_retries => retries += 1,
+                result => return result,
+            }
+        }
+    })
+}
+
+/// Initialize an array given a fallible function from index to element, keeping whatever succeeded on failure.
+///
+/// Once an index fails, indices after it are no longer started. On failure the error from the lowest failing index is returned along with every element that was completed, as `(index, element)` pairs in no particular order. Elements are moved out of the array rather than dropped.
+pub fn try_par_array_init_partial<Array, F, E>(
+    initializer: F,
+) -> Result<Array, (Partial<Array::Item>, E)>
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Result<Array::Item, E>,
+    Array::Item: Send,
+    E: Send,
+{
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    let units = rayon::iter::repeat_n((), Array::len());
+    let initializer = |i, ()| {
+        let _permit = Permit::acquire();
+        initializer(i)
+    };
+    match try_fill_par_iter(uninit_slots(&mut ret), units, initializer, &span) {
+        // Every slot was written
+        Ok(()) => Ok(unsafe { ret.assume_init() }),
+        Err(failure) => Err((failure.salvaged, failure.error)),
+    }
+}
+
+/// Initialize an array given a fallible function from index to element, calling `cleanup` on every completed element if the fill fails.
+///
+/// For elements that hold external resources, like file descriptors or GPU buffers, that should be released explicitly rather than only dropped. Behaves like `try_par_array_init`, but when an index fails or `initializer` panics, each element that was already completed is passed to `cleanup` exactly once and then dropped, before the error is returned or the panic carries on. On success `cleanup` is never called.
+/// If `cleanup` itself panics, the elements it hasn't reached yet are only dropped.
+pub fn try_par_array_init_with_cleanup<Array, F, C, E>(
+    initializer: F,
+    cleanup: C,
+) -> Result<Array, E>
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Result<Array::Item, E>,
+    C: Fn(&mut Array::Item),
+    Array::Item: Send,
+    E: Send,
+{
+    let result = try_par_array_init_partial(|i| {
+        // The panic is resumed below once the elements are cleaned up, so no one observes initializer after it
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| initializer(i))) {
+            Ok(Ok(elem)) => Ok(elem),
+            Ok(Err(error)) => Err(InitFailure::Error(error)),
+            Err(payload) => Err(InitFailure::Panic(payload)),
+        }
+    });
+    match result {
+        Ok(array) => Ok(array),
+        Err((salvaged, failure)) => {
+            for (_, mut elem) in salvaged {
+                cleanup(&mut elem);
+            }
+            match failure {
+                InitFailure::Error(error) => Err(error),
+                InitFailure::Panic(payload) => std::panic::resume_unwind(payload),
+            }
+        }
+    }
+}
+
+/// Initialize an array given a function from index to element, giving up once `deadline` has passed.
+///
+/// Indices are no longer started after the deadline, but calls to `initializer` that are already running are left to finish. If the array couldn't be completed in time the elements that were are returned, as `(index, element)` pairs in no particular order.
+pub fn par_array_init_deadline<Array, F>(
+    deadline: std::time::Instant,
+    initializer: F,
+) -> Result<Array, Partial<Array::Item>>
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    Array::Item: Send,
+{
+    try_par_array_init_partial(|i| {
+        if std::time::Instant::now() < deadline {
+            Ok(initializer(i))
+        } else {
+            Err(())
+        }
+    })
+    .map_err(|(salvaged, ())| salvaged)
+}
+
+/// Initialize an array given a function from index to element, filling the rest with clones of `sentinel` once an element satisfying `stop` turns up.
+///
+/// For search-like fills where nothing after a hit matters. Every call checks a shared flag first: once any element satisfies `stop`, indices that haven't been started yet are filled with `sentinel` instead of calling `initializer`. The element that hit keeps its value, and so do calls that were already running, so which slots end up as the sentinel depends on scheduling. In a single threaded pool it's exactly the slots after the first hit.
+#[must_use]
+pub fn par_array_init_until<Array, F, S>(initializer: F, stop: S, sentinel: Array::Item) -> Array
+where
+    Array: IsParArray,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+    S: Send + Sync + Fn(&Array::Item) -> bool,
+    Array::Item: Send + Sync + Clone,
+{
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    let stopped = AtomicBool::new(false);
+    let span = FillSpan::new(Array::len());
+    let mut ret = MaybeUninit::<Array>::uninit();
+    fill_par_fn(
+        uninit_slots(&mut ret),
+        default_min_len::<Array::Item>(Array::len()),
+        |i| {
+            if stopped.load(Ordering::Relaxed) {
+                return sentinel.clone();
+            }
+            let elem = initializer(i);
+            if stop(&elem) {
+                stopped.store(true, Ordering::Relaxed);
+            }
+            elem
+        },
+        &span,
+    );
+    // Every slot was written
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize an array given a fallible function from index to element, telling apart a returned error from a panic.
+///
+/// Behaves like `try_par_array_init`, except a panic in `initializer` is caught and returned as `InitFailure::Panic` rather than unwinding into the caller. Elements written before the failure are dropped either way.
+/// The caller can still use whatever `initializer` captured after a caught panic, so it has to be `UnwindSafe`. Wrap it in `AssertUnwindSafe` if nothing it captures can be left in a broken state.
+pub fn try_par_array_init_catch<Array, F, E>(initializer: F) -> Result<Array, InitFailure<E>>
+where
+    Array: IsParArray,
+    F: Send + Sync + std::panic::UnwindSafe + Fn(usize) -> Result<Array::Item, E>,
+    Array::Item: Send,
+    E: Send,
+{
+    // The array being filled lives inside the closure and is dropped by the unwind, so the closure is exactly as unwind safe as initializer
+    match std::panic::catch_unwind(|| try_par_array_init(initializer)) {
+        Ok(result) => result.map_err(InitFailure::Error),
+        Err(payload) => Err(InitFailure::Panic(payload)),
+    }
+}
+
+/// Like `try_par_array_init_catch`, but with the panic hook silenced for the duration of the fill so caught panics aren't printed.
+///
+/// A no-op panic hook is installed before the fill and the previous hook is put back afterward. The hook is global, so panics on any thread in the process are silenced while the fill runs.
+/// Quiet fills that overlap, including ones nested inside another quiet fill or run from a parallel iterator, share the installed hook: the first one to start installs it and the last one to finish puts the previous hook back. Nothing is locked while the fill runs, but code that sets its own panic hook at the same time may have it overwritten.
+pub fn try_par_array_init_catch_quiet<Array, F, E>(initializer: F) -> Result<Array, InitFailure<E>>
+where
+    Array: IsParArray,
+    F: Send + Sync + std::panic::UnwindSafe + Fn(usize) -> Result<Array::Item, E>,
+    Array::Item: Send,
+    E: Send,
+{
+    let _quiet = QuietHook::install();
+    try_par_array_init_catch(initializer)
+}
+
+/// Hook that was installed before the first of the quiet fills currently running, along with how many are running.
+static QUIET: std::sync::Mutex<(usize, Option<PanicHook>)> = std::sync::Mutex::new((0, None));
+
+type PanicHook = Box<dyn Fn(&std::panic::PanicHookInfo<'_>) + Sync + Send + 'static>;
+
+/// One running quiet fill's share of the no-op panic hook, the previous hook is put back when the last share is dropped.
+struct QuietHook;
+
+impl QuietHook {
+    fn install() -> QuietHook {
+        // Only held to update the count, never across a fill, so quiet fills on the same thread can't deadlock
+        let mut quiet = QUIET
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if quiet.0 == 0 {
+            quiet.1 = Some(std::panic::take_hook());
+            std::panic::set_hook(Box::new(|_| {}));
+        }
+        quiet.0 += 1;
+        QuietHook
+    }
+}
+
+impl Drop for QuietHook {
+    fn drop(&mut self) {
+        let mut quiet = QUIET
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        quiet.0 -= 1;
+        if quiet.0 == 0 {
+            if let Some(previous) = quiet.1.take() {
+                std::panic::set_hook(previous);
+            }
+        }
+    }
+}
+
+/// Error returned by `try_par_array_init_catch`.
+#[must_use]
+#[derive(Debug)]
+pub enum InitFailure<E> {
+    /// `initializer` returned this error.
+    Error(E),
+    /// `initializer` panicked, with this payload.
+    Panic(Box<dyn std::any::Any + Send>),
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for InitFailure<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InitFailure::Error(error) => write!(f, "initializer failed: {}", error),
+            InitFailure::Panic(_) => write!(f, "initializer panicked"),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for InitFailure<E> {}
+
+impl<E> From<E> for InitFailure<E> {
+    fn from(error: E) -> Self {
+        InitFailure::Error(error)
+    }
+}
+
+/// Elements that were completed before a fill was abandoned, as `(index, element)` pairs in no particular order.
+pub type Partial<T> = Vec<(usize, T)>;
+
+/// Fill in every `None` slot of an array given a function from index to element, leaving slots that are already `Some` untouched.
+///
+/// `initializer` is only called for the indices that are currently `None`.
+pub fn par_fill_missing<T, F, const N: usize>(array: &mut [Option<T>; N], initializer: F)
+where
+    T: Send,
+    F: Send + Sync + Fn(usize) -> T,
+{
+    let _span = FillSpan::new(N);
+    par_for_each(array.par_iter_mut().enumerate(), |(i, slot)| {
+        if slot.is_none() {
+            let _permit = Permit::acquire();
+            *slot = Some(initializer(i));
+        }
+    });
+}
+
+/// Finish initializing an array the caller partially filled, writing `initializer(i)` into every slot whose `filled` flag is false.
+///
+/// For staged initialization, the slots the caller already wrote are left untouched. If `initializer` panics nothing in `array` is dropped, the caller's elements and the ones written so far are leaked.
+///
+/// # Safety
+///
+/// Every slot of `array` whose `filled` flag is true must already be initialized.
+#[must_use]
+pub unsafe fn finish_par_init<T, F, const N: usize>(
+    mut array: [MaybeUninit<T>; N],
+    filled: &[bool; N],
+    initializer: F,
+) -> [T; N]
+where
+    T: Send,
+    F: Send + Sync + Fn(usize) -> T,
+{
+    let _span = FillSpan::new(N);
+    let slots = array.par_iter_mut().zip(filled.par_iter()).enumerate();
+    par_for_each(slots, |(i, (slot, &filled))| {
+        if !filled {
+            let _permit = Permit::acquire();
+            slot.write(initializer(i));
+        }
+    });
+    // The caller initialized the filled slots and we just wrote the rest
+    unsafe { (array.as_ptr() as *const [T; N]).read() }
+}
+
+/// Initialize a `Vec` of runtime length `len` given a function from index to element.
+///
+/// This is the dynamic length counterpart to `par_array_init`, the `Vec` is allocated once and then filled in place.
+#[must_use]
+pub fn par_vec_init<T, F>(len: usize, initializer: F) -> Vec<T>
+where
+    T: Send,
+    F: Send + Sync + Fn(usize) -> T,
+{
+    vec_init(len, initializer, &FillSpan::new(len))
+}
+
+/// Fill a `Vec` of `len` elements given a function from index to element, reporting to `span`.
+fn vec_init<T, F>(len: usize, initializer: F, span: &FillSpan) -> Vec<T>
+where
+    T: Send,
+    F: Send + Sync + Fn(usize) -> T,
+{
+    let mut ret = Vec::with_capacity(len);
+    fill_par_fn(
+        &mut ret.spare_capacity_mut()[..len],
+        default_min_len::<T>(len),
+        initializer,
+        span,
+    );
+    // The first len elements were all written
+    unsafe { ret.set_len(len) };
+    ret
+}
+
+/// Refill `buf` with `len` elements given a function from index to element, reusing its allocation.
+///
+/// `buf` is cleared first and only grows if its capacity is below `len`, so repeated fills of the same length allocate once. Returns the freshly filled elements.
+pub fn par_array_init_reuse<T, F>(buf: &mut Vec<T>, len: usize, initializer: F) -> &[T]
+where
+    T: Send,
+    F: Send + Sync + Fn(usize) -> T,
+{
+    let span = FillSpan::new(len);
+    buf.clear();
+    buf.reserve(len);
+    fill_par_fn(
+        &mut buf.spare_capacity_mut()[..len],
+        default_min_len::<T>(len),
+        initializer,
+        &span,
+    );
+    // The first len elements were all written
+    unsafe { buf.set_len(len) };
+    buf
+}
+
+/// Initialize a boxed slice of runtime length `len` given a function from index to element.
+///
+/// For data whose length never changes after it's built, a `Box<[T]>` skips the capacity a `Vec` carries around. The slice is allocated once and then filled in place.
+#[must_use]
+pub fn par_boxed_slice_init<T, F>(len: usize, initializer: F) -> Box<[T]>
+where
+    T: Send,
+    F: Send + Sync + Fn(usize) -> T,
+{
+    let span = FillSpan::new(len);
+    let mut ret = Box::new_uninit_slice(len);
+    fill_par_fn(&mut ret, default_min_len::<T>(len), initializer, &span);
+    // Every slot was written
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize a slice of uninitialized elements given a function from index to element.
+///
+/// Returns the now initialized slice. The elements are never dropped by this function, that's left to whoever owns the memory behind `slots`.
+pub fn par_fill_uninit<T, F>(slots: &mut [MaybeUninit<T>], initializer: F) -> &mut [T]
+where
+    T: Send,
+    F: Send + Sync + Fn(usize) -> T,
+{
+    let len = slots.len();
+    let span = FillSpan::new(len);
+    fill_par_fn(slots, default_min_len::<T>(len), initializer, &span);
+    // Every slot in 0..len was written
+    unsafe { std::slice::from_raw_parts_mut(slots.as_mut_ptr() as *mut T, len) }
+}
+
+/// Initialize a slice of uninitialized elements given a function from index to element, one element after another on the calling thread.
+///
+/// The sequential counterpart of `par_fill_uninit` for elements that can't be sent between threads. Returns the now initialized slice, if `initializer` panics the elements written so far are dropped.
+pub fn fill_uninit<T, F>(slots: &mut [MaybeUninit<T>], initializer: F) -> &mut [T]
+where
+    F: FnMut(usize) -> T,
+{
+    let len = slots.len();
+    fill_in_order(slots, initializer, &FillSpan::with_threads(len, 1));
+    // Every slot in 0..len was written
+    unsafe { std::slice::from_raw_parts_mut(slots.as_mut_ptr() as *mut T, len) }
+}
+
+/// Element types that choose whether `array_init_maybe_par` fills arrays of them in parallel or sequentially.
+///
+/// Stable Rust can't pick an implementation based on whether a type is `Send`, so element types opt in instead. `Send` types implement `fill` with `par_fill_uninit`, `!Send` types with `fill_uninit`.
+/// This is implemented for the primitive types and `String` (parallel) and for `Rc` and `rc::Weak` (sequential).
+pub trait MaybePar: Sized {
+    /// Write `initializer(i)` into every slot of `slots`.
+    fn fill<F>(slots: &mut [MaybeUninit<Self>], initializer: F)
+    where
+        F: Send + Sync + Fn(usize) -> Self;
+}
+
+macro_rules! impl_maybe_par {
+    ($fill:ident => $($t:ty),*) => {
+        $(impl MaybePar for $t {
+            fn fill<F>(slots: &mut [MaybeUninit<Self>], initializer: F)
+            where
+                F: Send + Sync + Fn(usize) -> Self,
+            {
+                $fill(slots, initializer);
+            }
+        })*
+    };
+}
+
+impl_maybe_par!(par_fill_uninit => u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, bool, char, String);
+
+impl<T> MaybePar for std::rc::Rc<T> {
+    fn fill<F>(slots: &mut [MaybeUninit<Self>], initializer: F)
+    where
+        F: Send + Sync + Fn(usize) -> Self,
+    {
+        fill_uninit(slots, initializer);
+    }
+}
+
+impl<T> MaybePar for std::rc::Weak<T> {
+    fn fill<F>(slots: &mut [MaybeUninit<Self>], initializer: F)
+    where
+        F: Send + Sync + Fn(usize) -> Self,
+    {
+        fill_uninit(slots, initializer);
+    }
+}
+
+/// Initialize an `Rc`ed array given a function from index to element, on the calling thread.
+///
+/// `Rc` can't be shared between threads, so this is a sequential fill for single threaded callers wanting cheap shared ownership. Neither the elements nor `initializer` have to be `Send`, and the array is written in place on the heap.
+#[must_use]
+pub fn array_init_rc<Array, F>(initializer: F) -> std::rc::Rc<Array>
+where
+    Array: IsParArray,
+    F: FnMut(usize) -> Array::Item,
+{
+    let mut ret = std::rc::Rc::<Array>::new_uninit();
+    // We just created the Rc so no one else can have a reference to it
+    let slot = std::rc::Rc::get_mut(&mut ret)
+        .unwrap_or_else(|| unreachable!("par_array_init: a newly created Rc was already shared"));
+    fill_uninit(uninit_slots(slot), initializer);
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize an array given a function from index to element, in parallel if the element type supports it.
+///
+/// Whether the fill runs in parallel is decided by the element type's `MaybePar` implementation, so arrays of `!Send` elements like `Rc` are filled sequentially instead of failing to compile.
+#[must_use]
+pub fn array_init_maybe_par<Array, F>(initializer: F) -> Array
+where
+    Array: IsParArray,
+    Array::Item: MaybePar,
+    F: Send + Sync + Fn(usize) -> Array::Item,
+{
+    let mut ret = MaybeUninit::<Array>::uninit();
+    Array::Item::fill(uninit_slots(&mut ret), initializer);
+    unsafe { ret.assume_init() }
+}
+
+/// Initialize the first `len` elements of a caller provided region, such as a slice into an mmap, given a function from index to element.
+///
+/// Returns `RegionError` without touching the region if it's too small to hold `len` elements, otherwise returns the initialized prefix.
+pub fn par_fill_region<T, F>(
+    region: &mut [MaybeUninit<T>],
+    len: usize,
+    initializer: F,
+) -> Result<&mut [T], RegionError>
+where
+    T: Send,
+    F: Send + Sync + Fn(usize) -> T,
+{
+    if region.len() < len {
+        return Err(RegionError {
+            expected: len,
+            actual: region.len(),
+        });
+    }
+    Ok(par_fill_uninit(&mut region[..len], initializer))
+}
+
+/// Error returned by `par_fill_region` when the region can't hold the requested number of elements.
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegionError {
+    /// Number of elements that were requested.
+    pub expected: usize,
+    /// Number of elements the region has room for.
+    pub actual: usize,
+}
+
+impl std::fmt::Display for RegionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "region has room for {} elements but {} were requested",
+            self.actual, self.expected
+        )
+    }
+}
+
+impl std::error::Error for RegionError {}
+
+/// Fill a byte buffer, such as a GPU staging buffer, with elements of type `T` given a function from index to element.
+///
+/// Requires the `bytemuck` feature. `dst` is reinterpreted as a `[T]` and filled in parallel, leaving the bytes ready to upload as is.
+/// Returns an error without writing anything if `dst` isn't aligned for `T`, or its length isn't a multiple of `size_of::<T>()`.
+#[cfg(feature = "bytemuck")]
+pub fn par_fill_bytes<T, F>(dst: &mut [u8], initializer: F) -> Result<(), FillError>
+where
+    T: bytemuck::Pod + Send,
+    F: Send + Sync + Fn(usize) -> T,
+{
+    let len = dst.len();
+    let elems: &mut [T] = bytemuck::try_cast_slice_mut(dst).map_err(|error| match error {
+        bytemuck::PodCastError::TargetAlignmentGreaterAndInputNotAligned
+        | bytemuck::PodCastError::AlignmentMismatch => FillError::Misaligned {
+            align: align_of::<T>(),
+        },
+        _ => FillError::Length {
+            len,
+            elem_size: size_of::<T>(),
+        },
+    })?;
+    let _span = FillSpan::new(elems.len());
+    let min_len = default_min_len::<T>(elems.len());
+    let elems = elems.par_iter_mut().with_min_len(min_len);
+    par_for_each(elems.enumerate(), |(i, elem)| {
+        let _permit = Permit::acquire();
+        *elem = initializer(i)
+    });
+    Ok(())
+}
+
+/// Error returned by `par_fill_shmem`, the region has the same requirements as the buffer passed to `par_fill_bytes`.
+#[cfg(feature = "shmem")]
+pub type ShmemError = FillError;
+
+/// Fill a process shared memory region, such as a POSIX shared memory mapping, with elements of type `T` given a function from index to element.
+///
+/// Requires the `shmem` feature. `region` is reinterpreted as a `[T]` and filled in parallel as with `par_fill_bytes`, erroring without writing anything if it isn't aligned for `T` or its length isn't a multiple of `size_of::<T>()`.
+/// Every write is finished by the time this returns and is followed by a release fence, so once the caller signals a process shared barrier or flag, even with a relaxed store, another process that acquires it sees the whole region.
+#[cfg(feature = "shmem")]
+pub fn par_fill_shmem<T, F>(region: &mut [u8], initializer: F) -> Result<(), ShmemError>
+where
+    T: bytemuck::Pod + Send,
+    F: Send + Sync + Fn(usize) -> T,
+{
+    par_fill_bytes(region, initializer)?;
+    std::sync::atomic::fence(std::sync::atomic::Ordering::Release);
+    Ok(())
+}
+
+/// Copy an array out of a byte buffer, such as a region of a memory mapped file, in parallel.
+///
+/// Requires the `bytemuck` feature. `bytes` is reinterpreted as a `[T]` and copied into a fresh array. Returns `None` if `bytes` isn't aligned for `T` or doesn't hold exactly `Array::len()` elements.
+#[cfg(feature = "bytemuck")]
+#[must_use]
+pub fn par_array_from_mmap<Array, T>(bytes: &[u8]) -> Option<Array>
+where
+    Array: IsParArray<Item = T>,
+    T: bytemuck::Pod + Send + Sync,
+{
+    let elems: &[T] = bytemuck::try_cast_slice(bytes).ok()?;
+    if elems.len() != Array::len() {
+        return None;
+    }
+    from_par_iter(elems.par_iter().copied())
+}
+
+/// Reinterpret an array of inner values as an array of a `#[repr(transparent)]` wrapper around them, without copying element by element.
+///
+/// Requires the `bytemuck` feature. For newtypes like `struct Id(u64)`: fill the `[u64; N]` with any of the `par_array_init` functions and wrap the result, so filling `[Id; N]` costs the same as filling `[u64; N]`. `TransparentWrapper` guarantees `W` and `I` share a layout, which makes this a move of the whole array.
+#[cfg(feature = "bytemuck")]
+#[must_use]
+pub fn from_inner_array<W, I, const N: usize>(array: [I; N]) -> [W; N]
+where
+    W: bytemuck::TransparentWrapper<I>,
+{
+    let array = std::mem::ManuallyDrop::new(array);
+    // SAFETY: `W` is `#[repr(transparent)]` over `I` so `[I; N]` and `[W; N]` have the same layout, and `array` won't be dropped so each element is only owned by the result
+    unsafe { std::ptr::read(array.as_ptr().cast::<[W; N]>()) }
+}
+
+/// Reinterpret an array of a `#[repr(transparent)]` wrapper as an array of the values it wraps, the inverse of `from_inner_array`.
+///
+/// Requires the `bytemuck` feature.
+#[cfg(feature = "bytemuck")]
+#[must_use]
+pub fn into_inner_array<W, I, const N: usize>(array: [W; N]) -> [I; N]
+where
+    W: bytemuck::TransparentWrapper<I>,
+{
+    let array = std::mem::ManuallyDrop::new(array);
+    // SAFETY: as in `from_inner_array`, with the roles of `W` and `I` swapped
+    unsafe { std::ptr::read(array.as_ptr().cast::<[I; N]>()) }
+}
+
+/// Number of elements `par_array_init_to_file` computes and holds in memory at a time.
+#[cfg(feature = "bytemuck")]
+pub const FILE_CHUNK_LEN: usize = 1 << 16;
+
+/// Write `count` elements given a function from index to element to the file at `path`, without holding all of them in memory.
+///
+/// Requires the `bytemuck` feature. For out of core arrays: the elements are computed in parallel in chunks of `FILE_CHUNK_LEN`, and each chunk is written out in index order as raw bytes before the next one is computed, so at most one chunk is in memory at a time. `path` is created, or truncated if it exists.
+/// The file is only the elements' bytes, so once mapped it can be read back with `par_array_from_mmap`.
+#[cfg(feature = "bytemuck")]
+pub fn par_array_init_to_file<T, F, P>(path: P, count: usize, initializer: F) -> std::io::Result<()>
+where
+    T: bytemuck::Pod + Send,
+    F: Send + Sync + Fn(usize) -> T,
+    P: AsRef<std::path::Path>,
+{
+    use std::io::Write;
+
+    let mut file = std::fs::File::create(path)?;
+    let _span = FillSpan::new(count);
+    for start in (0..count).step_by(FILE_CHUNK_LEN) {
+        // Each chunk is part of this fill's span
+        let chunk_len = FILE_CHUNK_LEN.min(count - start);
+        let chunk = vec_init(chunk_len, |i| initializer(start + i), &FillSpan::nested());
+        file.write_all(bytemuck::cast_slice(&chunk))?;
+    }
+    file.flush()
+}
+
+/// Error returned by `par_fill_bytes` when the buffer can't be viewed as a slice of elements.
+#[cfg(feature = "bytemuck")]
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillError {
+    /// The buffer isn't aligned to `align` bytes.
+    Misaligned {
+        /// Alignment the element type requires.
+        align: usize,
+    },
+    /// The buffer's `len` isn't a multiple of the element size.
+    Length {
+        /// Length of the buffer in bytes.
+        len: usize,
+        /// Size of the element type in bytes.
+        elem_size: usize,
+    },
+}
+
+#[cfg(feature = "bytemuck")]
+impl std::fmt::Display for FillError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FillError::Misaligned { align } => {
+                write!(f, "buffer is not aligned to {} bytes", align)
+            }
+            FillError::Length { len, elem_size } => write!(
+                f,
+                "buffer of {} bytes can't hold a whole number of {} byte elements",
+                len, elem_size
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "bytemuck")]
+impl std::error::Error for FillError {}
+
+/// Write every slot of `slots` in parallel from the elements of `iter`.
+///
+/// If anything panics partway through, every element written so far is dropped before the panic continues, so the caller never sees a partially initialized slice.
+/// `iter` must have atleast as many elements as `slots`, if it turns out to have fewer (i.e. its `len()` lied) the written elements are dropped and this panics rather than leaving slots uninitialized.
+/// A single slot is written from the iterator's first element on the current thread.
+fn fill_par_iter<T, I>(slots: &mut [MaybeUninit<T>], iter: I, span: &FillSpan)
+where
+    T: Send,
+    I: IndexedParallelIterator<Item = T>,
+{
+    let len = slots.len();
+    if len == 1 {
+        let elem = iter.with_producer(FirstElem).unwrap_or_else(|| {
+            unreachable!("par_array_init: iterator promised atleast 1 element but produced none")
+        });
+        slots[0].write(elem);
+        span.chunk(0, 1);
+        return;
+    }
+    // Zipping with the indices benches the same as `iter.take(len).enumerate()`, see the `from_iter` bench
+    let indexed = (0..len).into_par_iter().zip(iter);
+    let written = write_indexed(slots, indexed, |elem| elem, span);
+    if written.len != len {
+        unreachable!(
+            "par_array_init: iterator promised atleast {} elements but only produced {}",
+            len, written.len
+        );
+    }
+    std::mem::forget(written);
+}
+
+/// Limit set by `set_global_concurrency_limit`, 0 when there is none.
+static CONCURRENCY_LIMIT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+/// Number of permits currently held.
+static PERMITS: std::sync::Mutex<usize> = std::sync::Mutex::new(0);
+static PERMIT_FREED: std::sync::Condvar = std::sync::Condvar::new();
+
+/// A permit to run one initializer call under the global concurrency limit, given back when it's dropped.
+struct Permit;
+
+impl Permit {
+    /// Wait for a permit, or return `None` straight away if there's no limit.
+    fn acquire() -> Option<Permit> {
+        use std::sync::atomic::Ordering;
+
+        if CONCURRENCY_LIMIT.load(Ordering::Relaxed) == 0 {
+            return None;
+        }
+        let mut in_use = PERMITS.lock().unwrap_or_else(|poison| poison.into_inner());
+        loop {
+            let limit = CONCURRENCY_LIMIT.load(Ordering::Relaxed);
+            if limit == 0 {
+                return None;
+            }
+            if *in_use < limit {
+                *in_use += 1;
+                return Some(Permit);
+            }
+            in_use = PERMIT_FREED
+                .wait(in_use)
+                .unwrap_or_else(|poison| poison.into_inner());
+        }
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        *PERMITS.lock().unwrap_or_else(|poison| poison.into_inner()) -= 1;
+        PERMIT_FREED.notify_one();
+    }
+}
+
+/// Takes the first element of an indexed parallel iterator on the current thread, without going through the thread pool.
+struct FirstElem;
+
+impl<T> ProducerCallback<T> for FirstElem {
+    type Output = Option<T>;
+
+    fn callback<P: Producer<Item = T>>(self, producer: P) -> Option<T> {
+        producer.into_iter().next()
+    }
+}
+
+/// Write `initializer(i)` into every slot in parallel, splitting into tasks of at least `min_len` slots.
+///
+/// This is the common case of filling from `(0..len).into_par_iter().map(initializer)`, and calls `initializer` directly rather than zipping the index range against a second parallel iterator.
+/// A single slot is written on the current thread, there's nothing to split.
+#[cfg(not(any(
+    feature = "deterministic",
+    all(target_arch = "wasm32", not(target_feature = "atomics"))
+)))]
+fn fill_par_fn<T, F>(slots: &mut [MaybeUninit<T>], min_len: usize, initializer: F, span: &FillSpan)
+where
+    T: Send,
+    F: Send + Sync + Fn(usize) -> T,
+{
+    let initializer = |i| {
+        let _permit = Permit::acquire();
+        initializer(i)
+    };
+    if let [slot] = slots {
+        slot.write(initializer(0));
+        span.chunk(0, 1);
+        return;
+    }
+    let indices = (0..slots.len()).into_par_iter().with_min_len(min_len);
+    let written = write_indexed(slots, indices, |i| (i, initializer(i)), span);
+    // The index range covers every slot
+    std::mem::forget(written);
+}
+
+/// Without threads to run on, or with the `deterministic` feature, fill every slot one after another on the current thread.
+#[cfg(any(
+    feature = "deterministic",
+    all(target_arch = "wasm32", not(target_feature = "atomics"))
+))]
+fn fill_par_fn<T, F>(slots: &mut [MaybeUninit<T>], _min_len: usize, initializer: F, span: &FillSpan)
+where
+    T: Send,
+    F: Send + Sync + Fn(usize) -> T,
+{
+    fill_in_order(slots, initializer, span);
+}
+
+/// Whether fills run one after another on the calling thread instead of going through rayon, with the `deterministic` feature or on WebAssembly without threads.
+///
+/// Only `fill_par_fn` is switched by `cfg`, everywhere else rayon's parallel iterators have to be named anyway so both paths are compiled and this picks one.
+const SEQUENTIAL: bool = cfg!(any(
+    feature = "deterministic",
+    all(target_arch = "wasm32", not(target_feature = "atomics"))
+));
+
+/// Write `initializer(i)` into every slot in ascending index order on the current thread, for `fill_uninit` and the fill functions fall back to when `SEQUENTIAL`.
+///
+/// If `initializer` panics the elements written so far are dropped.
+fn fill_in_order<T, F>(slots: &mut [MaybeUninit<T>], mut initializer: F, span: &FillSpan)
+where
+    F: FnMut(usize) -> T,
+{
+    let len = slots.len();
+    let mut written = Written::new(slots.as_mut_ptr() as *mut T);
+    for i in 0..len {
+        let _permit = Permit::acquire();
+        written.push(i, initializer(i));
+    }
+    std::mem::forget(written);
+    span.chunk(0, len);
+}
+
+/// Call `f` on every item of `iter`, in parallel, or in order on the current thread when `SEQUENTIAL`.
+fn par_for_each<I, F>(iter: I, f: F)
+where
+    I: IndexedParallelIterator,
+    F: Send + Sync + Fn(I::Item),
+{
+    if SEQUENTIAL {
+        for_each_in_order(iter, f);
+    } else {
+        iter.for_each(f);
+    }
+}
+
+/// Collect `f` of every item of `iter` into a `Vec` in order, computed in parallel, or one after another on the current thread when `SEQUENTIAL`.
+fn par_map_collect<I, R, F>(iter: I, f: F) -> Vec<R>
+where
+    I: IndexedParallelIterator,
+    R: Send,
+    F: Send + Sync + Fn(I::Item) -> R,
+{
+    if SEQUENTIAL {
+        let mut collected = Vec::with_capacity(iter.len());
+        for_each_in_order(iter, |item| collected.push(f(item)));
+        collected
+    } else {
+        iter.map(f).collect()
+    }
+}
+
+/// Call `f` on every item of `iter` in order on the current thread, without going through the thread pool.
+fn for_each_in_order<I, F>(iter: I, mut f: F)
+where
+    I: IndexedParallelIterator,
+    F: FnMut(I::Item),
+{
+    let done: Result<(), std::convert::Infallible> = try_for_each_in_order(iter, |item| {
+        f(item);
+        Ok(())
+    });
+    match done {
+        Ok(()) => {}
+        Err(never) => match never {},
+    }
+}
+
+/// Call `f` on every item of `iter` in order on the current thread until it returns an error, without going through the thread pool.
+fn try_for_each_in_order<I, E, F>(iter: I, f: F) -> Result<(), E>
+where
+    I: IndexedParallelIterator,
+    F: FnMut(I::Item) -> Result<(), E>,
+{
+    iter.with_producer(InOrder(f))
+}
+
+/// Walks the producer of an indexed parallel iterator as a plain sequential iterator, for `try_for_each_in_order`.
+struct InOrder<F>(F);
+
+impl<T, E, F: FnMut(T) -> Result<(), E>> ProducerCallback<T> for InOrder<F> {
+    type Output = Result<(), E>;
+
+    fn callback<P: Producer<Item = T>>(self, producer: P) -> Result<(), E> {
+        producer.into_iter().try_for_each(self.0)
+    }
+}
+
+/// Shared implementation of `fill_par_iter` and `fill_par_fn`, writing each `(index, element)` produced by `produce` into its slot.
+///
+/// `iter` has to visit indices in order starting from 0 for `Written` to be able to merge the runs.
+/// Returns the run of slots that were written, it's up to the caller to forget it once they've taken ownership of the elements.
+fn write_indexed<T, I, P>(
+    slots: &mut [MaybeUninit<T>],
+    iter: I,
+    produce: P,
+    span: &FillSpan,
+) -> Written<T>
+where
+    T: Send,
+    I: IndexedParallelIterator,
+    P: Send + Sync + Fn(I::Item) -> (usize, T),
+{
+    // Every write goes through this base pointer so it's valid for the whole of slots
+    let origin = Written::new(slots.as_mut_ptr() as *mut T);
+    if SEQUENTIAL {
+        let mut written = origin.empty();
+        for_each_in_order(iter, |item| {
+            let (i, elem) = produce(item);
+            written.push(i, elem);
+        });
+        span.chunk(written.start, written.len);
+        return written;
+    }
+    iter.fold(
+        || origin.empty(),
+        |mut written, item| {
+            let (i, elem) = produce(item);
+            written.push(i, elem);
+            written
+        },
+    )
+    .inspect(|written| span.chunk(written.start, written.len))
+    .reduce(|| origin.empty(), Written::merge)
+}
+
+/// `tracing` span covering a single fill, recording the fill's length, thread count and duration.
+///
+/// Every public function that fills an array starts exactly one of these before it writes anything, arrays of 0 or 1 elements included, and hands it to the helpers doing the writing so they can report their chunks. Fills that are only a step of a bigger one, like the batches of `par_array_init_double_buffered`, run under `FillSpan::nested` so they aren't counted as fills of their own.
+/// With the `metrics` feature each fill also increments the `par_array_init.calls` and `par_array_init.elements` counters when it starts and records its duration in seconds to the `par_array_init.duration` histogram when it ends.
+/// Without either feature this is empty and every method is a no-op.
+struct FillSpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    // None for a nested fill, which records nothing
+    #[cfg(any(feature = "tracing", feature = "metrics"))]
+    start: Option<std::time::Instant>,
+}
+
+impl FillSpan {
+    /// Start the span of a fill of `len` elements on the current pool, or on the calling thread when `SEQUENTIAL`.
+    #[cfg(any(feature = "tracing", feature = "metrics"))]
+    fn new(len: usize) -> Self {
+        let num_threads = if SEQUENTIAL {
+            1
+        } else {
+            rayon::current_num_threads()
+        };
+        FillSpan::with_threads(len, num_threads)
+    }
+
+    #[cfg(not(any(feature = "tracing", feature = "metrics")))]
+    #[inline(always)]
+    fn new(_len: usize) -> Self {
+        FillSpan {}
+    }
+
+    /// Start the span of a fill of `len` elements that runs on `num_threads` threads whatever the current pool is.
+    #[cfg(any(feature = "tracing", feature = "metrics"))]
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    fn with_threads(len: usize, num_threads: usize) -> Self {
+        #[cfg(feature = "metrics")]
+        {
+            metrics::counter!("par_array_init.calls").increment(1);
+            metrics::counter!("par_array_init.elements").increment(len as u64);
+        }
+        FillSpan {
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!(
+                "par_array_init",
+                len,
+                num_threads,
+                duration_us = tracing::field::Empty
+            ),
+            start: Some(std::time::Instant::now()),
+        }
+    }
+
+    #[cfg(not(any(feature = "tracing", feature = "metrics")))]
+    #[inline(always)]
+    fn with_threads(_len: usize, _num_threads: usize) -> Self {
+        FillSpan {}
+    }
+
+    /// Span of a fill that's part of another fill's span, which records nothing.
+    #[cfg(any(feature = "tracing", feature = "metrics"))]
+    fn nested() -> Self {
+        FillSpan {
+            #[cfg(feature = "tracing")]
+            span: tracing::Span::none(),
+            start: None,
+        }
+    }
+
+    #[cfg(not(any(feature = "tracing", feature = "metrics")))]
+    #[inline(always)]
+    fn nested() -> Self {
+        FillSpan {}
+    }
+
+    /// Report the chunk of `len` slots starting at `start` that a single task filled.
+    #[cfg(feature = "tracing")]
+    fn chunk(&self, start: usize, len: usize) {
+        if self.start.is_some() {
+            tracing::debug!(parent: &self.span, start, len, "filled chunk");
+        }
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    #[inline(always)]
+    fn chunk(&self, _start: usize, _len: usize) {}
+}
+
+#[cfg(any(feature = "tracing", feature = "metrics"))]
+impl Drop for FillSpan {
+    fn drop(&mut self) {
+        let elapsed = match self.start {
+            Some(start) => start.elapsed(),
+            None => return,
+        };
+        #[cfg(feature = "tracing")]
+        self.span.record("duration_us", elapsed.as_micros() as u64);
+        #[cfg(feature = "metrics")]
+        metrics::histogram!("par_array_init.duration").record(elapsed.as_secs_f64());
+    }
+}
+
+/// Tracks a contiguous run of slots that have been written, dropping them if it's dropped itself.
+///
+/// Rayon folds each split of the fill sequentially and reduces neighbouring splits in order, so runs only ever need to be merged with the run directly after them.
+///
+/// There's no separate path for `Copy` elements: for any `T` that doesn't need dropping `drop_in_place` compiles to nothing, so after monomorphization the guard is only the index bookkeeping.
+struct Written<T> {
+    base: *mut T,
+    start: usize,
+    len: usize,
+}
+
+// Written only touches the slots it's been given, which no one else writes to, so it's as Send as the elements
+unsafe impl<T: Send> Send for Written<T> {}
+unsafe impl<T: Send> Sync for Written<T> {}
+
+impl<T> Written<T> {
+    fn new(base: *mut T) -> Self {
+        Written {
+            base,
+            start: 0,
+            len: 0,
+        }
+    }
+
+    fn empty(&self) -> Self {
+        Written::new(self.base)
+    }
+
+    fn push(&mut self, i: usize, elem: T) {
+        assert!(
+            self.len == 0 || self.start + self.len == i,
+            "par_array_init: slots were written out of order"
+        );
+        unsafe { self.base.add(i).write(elem) };
+        if self.len == 0 {
+            self.start = i;
+        }
+        self.len += 1;
+    }
+
+    fn merge(self, other: Self) -> Self {
+        if self.len == 0 {
+            return other;
+        }
+        if other.len == 0 {
+            return self;
+        }
+        // Dropping both on a failed assert is fine, they still own disjoint runs
+        assert!(
+            self.start + self.len == other.start,
+            "par_array_init: slots were written out of order"
+        );
+        let merged = Written {
+            base: self.base,
+            start: self.start,
+            len: self.len + other.len,
+        };
+        std::mem::forget(self);
+        std::mem::forget(other);
+        merged
+    }
+}
+
+impl<T> Drop for Written<T> {
+    fn drop(&mut self) {
+        unsafe {
+            std::ptr::drop_in_place(std::ptr::slice_from_raw_parts_mut(
+                self.base.add(self.start),
+                self.len,
+            ))
+        }
+    }
+}
+
+impl<T> Written<T> {
+    /// Move the elements of this run out as `(index, element)` pairs.
+    fn salvage(self, salvaged: &mut Partial<T>) {
+        salvaged.reserve(self.len);
+        for i in self.start..self.start + self.len {
+            salvaged.push((i, unsafe { self.base.add(i).read() }));
+        }
+        std::mem::forget(self);
+    }
+}
+
+/// A pointer into a side buffer that every task of a fill writes its own slots of, like the encodings of `par_array_init_encoded`.
+///
+/// Unlike `Written` this doesn't own or drop anything, the buffer has to be initialized (or be fine to leave uninitialized) on its own.
+struct SendPtr<T>(*mut T);
+
+// Each task only writes the slots for its own indices, which no other task reads or writes, and the buffer outlives the fill, so sharing the pointer is as safe as sending the elements
+unsafe impl<T: Send> Send for SendPtr<T> {}
+unsafe impl<T: Send> Sync for SendPtr<T> {}
+
+impl<T> SendPtr<T> {
+    /// Pointer to slot `i` of the buffer, which has to be in bounds.
+    unsafe fn add(&self, i: usize) -> *mut T {
+        self.0.add(i)
+    }
+}
+
+/// Why a `try_fill_par_iter` didn't fill every slot.
+struct Failure<T, E> {
+    /// Error from the lowest index that failed.
+    error: E,
+    /// Elements that were written before the fill was abandoned.
+    salvaged: Partial<T>,
+}
+
+/// Fold state for `try_fill_par_iter`.
+///
+/// A split stays `Complete` as long as it has written every element it has seen, so like `Written::merge` completed neighbours are always adjacent.
+/// As soon as a split fails or skips an element it becomes `Failed` and its written elements are salvaged, which doesn't need them to be contiguous.
+enum TryWritten<T, E> {
+    Complete(Written<T>),
+    Failed {
+        error: Option<(usize, E)>,
+        salvaged: Partial<T>,
+    },
+}
+
+impl<T, E> TryWritten<T, E> {
+    fn into_failed(self) -> (Option<(usize, E)>, Partial<T>) {
+        match self {
+            TryWritten::Complete(written) => {
+                let mut salvaged = Vec::new();
+                written.salvage(&mut salvaged);
+                (None, salvaged)
+            }
+            TryWritten::Failed { error, salvaged } => (error, salvaged),
+        }
+    }
+
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (TryWritten::Complete(a), TryWritten::Complete(b)) => TryWritten::Complete(a.merge(b)),
+            (a, b) => {
+                let (a_error, mut salvaged) = a.into_failed();
+                let (b_error, b_salvaged) = b.into_failed();
+                salvaged.extend(b_salvaged);
+                let error = match (a_error, b_error) {
+                    (Some(a), Some(b)) => Some(if a.0 <= b.0 { a } else { b }),
+                    (a, b) => a.or(b),
+                };
+                TryWritten::Failed { error, salvaged }
+            }
+        }
+    }
+}
+
+/// Fallible version of `fill_par_iter`, writing `f(i, elem)` for each element of `iter` until every slot is full.
+///
+/// An index is only started if no lower index has failed yet, so the lowest failing index is always run. Reducing two failed runs keeps the error with the lower index, so it's always the one reported, however the failures raced.
+/// On failure every written element is moved into `Failure::salvaged` and ownership of them passes to the caller.
+fn try_fill_par_iter<T, E, I, F>(
+    slots: &mut [MaybeUninit<T>],
+    iter: I,
+    f: F,
+    span: &FillSpan,
+) -> Result<(), Failure<T, E>>
+where
+    T: Send,
+    E: Send,
+    I: IndexedParallelIterator,
+    F: Send + Sync + Fn(usize, I::Item) -> Result<T, E>,
+{
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let len = slots.len();
+    let origin = Written::new(slots.as_mut_ptr() as *mut T);
+    if SEQUENTIAL {
+        let mut written = origin.empty();
+        let indexed = (0..len).into_par_iter().zip(iter);
+        let done = try_for_each_in_order(indexed, |(i, elem)| {
+            written.push(i, f(i, elem)?);
+            Ok(())
+        });
+        span.chunk(written.start, written.len);
+        return match done {
+            Ok(()) if written.len == len => {
+                std::mem::forget(written);
+                Ok(())
+            }
+            Ok(()) => unreachable!("par_array_init: iterator was shorter than the array"),
+            Err(error) => {
+                let mut salvaged = Vec::new();
+                written.salvage(&mut salvaged);
+                Err(Failure { error, salvaged })
+            }
+        };
+    }
+    let first_failure = AtomicUsize::new(usize::MAX);
+    let written = (0..len)
+        .into_par_iter()
+        .zip(iter)
+        .fold(
+            || TryWritten::Complete(origin.empty()),
+            |written, (i, elem)| {
+                if i > first_failure.load(Ordering::Relaxed) {
+                    return written.merge(TryWritten::Failed {
+                        error: None,
+                        salvaged: Vec::new(),
+                    });
+                }
+                match (written, f(i, elem)) {
+                    (TryWritten::Complete(mut written), Ok(elem)) => {
+                        written.push(i, elem);
+                        TryWritten::Complete(written)
+                    }
+                    (
+                        TryWritten::Failed {
+                            error,
+                            mut salvaged,
+                        },
+                        Ok(elem),
+                    ) => {
+                        salvaged.push((i, elem));
+                        TryWritten::Failed { error, salvaged }
+                    }
+                    (written, Err(error)) => {
+                        first_failure.fetch_min(i, Ordering::Relaxed);
+                        written.merge(TryWritten::Failed {
+                            error: Some((i, error)),
+                            salvaged: Vec::new(),
+                        })
+                    }
+                }
+            },
+        )
+        .inspect(|written| {
+            if let TryWritten::Complete(written) = written {
+                span.chunk(written.start, written.len)
+            }
+        })
+        .reduce(|| TryWritten::Complete(origin.empty()), TryWritten::merge);
+    match written {
+        TryWritten::Complete(written) if written.len == len => {
+            std::mem::forget(written);
+            Ok(())
+        }
+        TryWritten::Complete(_) => {
+            unreachable!("par_array_init: iterator was shorter than the array")
+        }
+        TryWritten::Failed { error, salvaged } => {
+            let (_, error) = error.unwrap_or_else(|| {
+                unreachable!("par_array_init: elements were skipped without any failing")
+            });
+            Err(Failure { error, salvaged })
+        }
+    }
+}
+
+/// View an uninitialized array as a slice of its uninitialized elements.
+fn uninit_slots<Array: IsParArray>(
+    array: &mut MaybeUninit<Array>,
+) -> &mut [MaybeUninit<Array::Item>] {
+    // IsParArray guarantees the array is laid out as Array::len() contiguous items
+    unsafe {
+        std::slice::from_raw_parts_mut(
+            array.as_mut_ptr() as *mut MaybeUninit<Array::Item>,
+            Array::len(),
+        )
+    }
+}
+
+/// Trait for fixed size arrays that can be initialized in parallel.
+///
+/// Includes a method to convert the array to a mutable slice.
+/// `&mut [T]` implements parallel iterator so this allows us to write our elements in parallel without having to explicitly pass a `*mut T` across threads.
+///
+/// # Safety
+/// Implementors must be laid out as `len()` contiguous `Item`s, this is implemented for every `[T; N]` and probably shouldn't be implemented yourself.
+pub unsafe trait IsParArray {
+    /// Type of the array's elements.
+    type Item;
+    /// Number of elements in the array.
+    fn len() -> usize;
+    /// Convert array to a mutable slice.
+    fn mut_slice(&mut self) -> &mut [Self::Item];
+    /// View the array as a slice, e.g. to pass a filled array across an FFI boundary.
+    ///
+    /// Arrays have no padding between elements, so the slice starts at the array's own address and covers exactly `len() * size_of::<Item>()` bytes.
+    fn as_ffi_slice(&self) -> &[Self::Item] {
+        // Implementors are laid out as len() contiguous Items
+        unsafe { std::slice::from_raw_parts(std::ptr::from_ref(self).cast(), Self::len()) }
+    }
+}
+
+unsafe impl<T, const N: usize> IsParArray for [T; N] {
+    type Item = T;
+
+    fn len() -> usize {
+        N
+    }
+
+    fn mut_slice(&mut self) -> &mut [Self::Item] {
+        self
+    }
+}
+
+/// An `N` element array that carries the crate's fills around with it as methods.
+///
+/// A transparent wrapper around `[T; N]` that derefs to the inner array, so it can be indexed, iterated and sliced like one. `ParArray::init` and the combinators build new arrays in parallel, and it works with every function here that takes an `IsParArray`.
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ParArray<T, const N: usize>(pub [T; N]);
+
+impl<T: Send, const N: usize> ParArray<T, N> {
+    /// Initialize the array given a function from index to element, like `par_array_init`.
+    #[must_use]
+    pub fn init<F>(initializer: F) -> Self
+    where
+        F: Send + Sync + Fn(usize) -> T,
+    {
+        ParArray(par_array_init(initializer))
+    }
+
+    /// Map each element to a new array in parallel, like `par_array_map`.
+    #[must_use]
+    pub fn par_map<U, F>(self, f: F) -> ParArray<U, N>
+    where
+        U: Send,
+        F: Send + Sync + Fn(T) -> U,
+    {
+        ParArray(par_array_map(self.0, f))
+    }
+
+    /// Combine this array with another element by element in parallel, like `par_array_zip_map`.
+    #[must_use]
+    pub fn par_zip_map<U, V, F>(self, other: ParArray<U, N>, f: F) -> ParArray<V, N>
+    where
+        U: Send,
+        V: Send,
+        F: Send + Sync + Fn(T, U) -> V,
+    {
+        ParArray(par_array_zip_map(self.0, other.0, f))
+    }
+}
+
+impl<T, const N: usize> ParArray<T, N> {
+    /// Unwrap the inner array.
+    pub fn into_inner(self) -> [T; N] {
+        self.0
+    }
+}
+
+impl<T, const N: usize> std::ops::Deref for ParArray<T, N> {
+    type Target = [T; N];
+
+    fn deref(&self) -> &[T; N] {
+        &self.0
+    }
+}
+
+impl<T, const N: usize> std::ops::DerefMut for ParArray<T, N> {
+    fn deref_mut(&mut self) -> &mut [T; N] {
+        &mut self.0
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for ParArray<T, N> {
+    fn from(array: [T; N]) -> Self {
+        ParArray(array)
+    }
+}
+
+impl<T, const N: usize> From<ParArray<T, N>> for [T; N] {
+    fn from(array: ParArray<T, N>) -> Self {
+        array.0
+    }
+}
+
+// ParArray is a transparent wrapper, so it's laid out exactly like [T; N]
+unsafe impl<T, const N: usize> IsParArray for ParArray<T, N> {
+    type Item = T;
+
+    fn len() -> usize {
+        N
+    }
+
+    fn mut_slice(&mut self) -> &mut [Self::Item] {
+        &mut self.0
+    }
+}
+
+impl<T, const N: usize> FromParallelArray for ParArray<T, N> {
+    type Item = T;
+
+    fn from_par_fn<F>(initializer: F) -> Self
+    where
+        F: Send + Sync + Fn(usize) -> T,
+        T: Send,
+    {
+        ParArray(par_array_init(initializer))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_array_init_in_parallel_works() {
+        let array: [usize; 10] = par_array_init(|i| i);
+        for (indx, i) in array.iter().enumerate() {
+            assert_eq!(*i, indx)
+        }
+    }
+
+    #[test]
+    #[allow(clippy::useless_vec)]
+    fn test_array_init_with_capturing_lambda_works() {
+        let vec = vec![40; 10];
+        let array: [usize; 10] = par_array_init(|i| vec[i]);
+        for i in array.iter() {
+            assert_eq!(*i, 40);
+        }
+    }
+
+    #[test]
+    fn test_from_iter_works() {
+        let vec = vec![1, 2, 3, 4];
+        let array: Option<[usize; 4]> = from_par_iter(vec.into_par_iter());
+        assert_eq!(array, Some([1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn test_from_iter_fails_when_len_is_different() {
+        let vec = vec![1, 2, 3, 4];
+        let array: Option<[usize; 10]> = from_par_iter(vec.into_par_iter());
+        assert_eq!(array, None);
+    }
+
+    #[test]
+    fn test_initialization_works_with_larger_types() {
+        let vec = vec![
+            "A neat string",
+            "A lame string",
+            "A longish string abcdefghijklmnopqrstuvwxyz",
+        ];
+        let array: Option<[&str; 3]> = from_par_iter(vec.into_par_iter());
+        assert_eq!(
+            array,
+            Some([
+                "A neat string",
+                "A lame string",
+                "A longish string abcdefghijklmnopqrstuvwxyz"
+            ])
+        )
+    }
+
+    #[test]
+    fn test_par_array_indices_works() {
+        let array: [usize; 64] = par_array_indices();
+        for (indx, i) in array.iter().enumerate() {
+            assert_eq!(*i, indx)
+        }
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn test_par_array_zeroed_is_all_zero() {
+        let array: [u32; 1000] = par_array_zeroed();
+        assert_eq!(&array[..], &[0u32; 1000][..]);
+    }
+
+    #[test]
+    fn test_from_iter_padded_fills_tail_with_pad() {
+        let vec = vec![1, 2, 3];
+        let array: [i32; 6] = from_par_iter_padded(vec.into_par_iter(), -1);
+        assert_eq!(array, [1, 2, 3, -1, -1, -1]);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_par_array_init_rng_is_reproducible() {
+        use rand::Rng;
+        let first: [u32; 16] = par_array_init_rng(42, |rng, _| rng.gen());
+        let second: [u32; 16] = par_array_init_rng(42, |rng, _| rng.gen());
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_par_array_map_works_with_non_copy_output() {
+        let array: [u32; 8] = par_array_init(|i| i as u32);
+        let strings: [String; 8] = par_array_map(array, |i| i.to_string());
+        for (indx, s) in strings.iter().enumerate() {
+            assert_eq!(*s, indx.to_string());
+        }
+    }
+
+    #[test]
+    fn test_par_array_map_drops_elements_on_panic() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CREATED: AtomicUsize = AtomicUsize::new(0);
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+        struct Tracked;
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let src: [usize; 64] = par_array_indices();
+        let result = std::panic::catch_unwind(|| {
+            par_array_map(src, |i| {
+                if i == 40 {
+                    panic!("boom");
+                }
+                CREATED.fetch_add(1, Ordering::SeqCst);
+                Tracked
+            })
+        });
+        assert!(result.is_err());
+        assert_eq!(DROPS.load(Ordering::SeqCst), CREATED.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_par_array_zip_map_sums_elements() {
+        let a = [1, 2, 3, 4];
+        let b = [10, 20, 30, 40];
+        let sums: [i32; 4] = par_array_zip_map(a, b, |a, b| a + b);
+        assert_eq!(sums, [11, 22, 33, 44]);
+    }
+
+    #[test]
+    fn test_par_fill_region_fills_vec_backed_region() {
+        let mut region: Vec<MaybeUninit<u64>> = (0..16).map(|_| MaybeUninit::uninit()).collect();
+        let filled = par_fill_region(&mut region, 12, |i| (i * 2) as u64).unwrap();
+        assert_eq!(filled.len(), 12);
+        for (indx, i) in filled.iter().enumerate() {
+            assert_eq!(*i, (indx * 2) as u64);
+        }
+    }
+
+    #[test]
+    fn test_par_fill_region_rejects_small_region() {
+        let mut region: Vec<MaybeUninit<u64>> = (0..4).map(|_| MaybeUninit::uninit()).collect();
+        let result = par_fill_region(&mut region, 8, |i| i as u64);
+        assert_eq!(
+            result.unwrap_err(),
+            RegionError {
+                expected: 8,
+                actual: 4
+            }
+        );
+    }
+
+    #[test]
+    fn test_try_par_array_init_partial_succeeds() {
+        let array: Result<[usize; 8], _> = try_par_array_init_partial(Ok::<_, ()>);
+        assert_eq!(array.unwrap(), [0, 1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_try_par_array_init_partial_returns_completed_elements() {
+        let result: Result<[String; 8], _> = try_par_array_init_partial(|i| {
+            if i == 4 {
+                Err("index 4 failed")
+            } else {
+                Ok(i.to_string())
+            }
+        });
+        let (mut partial, error) = result.unwrap_err();
+        assert_eq!(error, "index 4 failed");
+        partial.sort();
+        assert!(partial.iter().all(|(i, s)| *i != 4 && *s == i.to_string()));
+        for i in 0..4 {
+            assert!(partial.iter().any(|(indx, _)| *indx == i));
+        }
+    }
+
+    #[test]
+    fn test_from_fn_matches_std() {
+        let std_array: [usize; 5] = std::array::from_fn(|i| i * 2);
+        let par_array: [usize; 5] = from_fn(|i| i * 2);
+        assert_eq!(std_array, par_array);
+        assert_eq!(
+            std::array::from_fn::<_, 4, _>(|i| i),
+            from_fn::<_, 4, _>(|i| i)
+        );
+    }
+
+    #[test]
+    fn test_par_fill_missing_only_fills_none() {
+        let mut array: [Option<usize>; 8] =
+            par_array_init(|i| if i % 2 == 0 { Some(100 + i) } else { None });
+        par_fill_missing(&mut array, |i| {
+            assert!(i % 2 == 1, "called for pre-filled index {}", i);
+            i
+        });
+        assert_eq!(
+            array,
+            [
+                Some(100),
+                Some(1),
+                Some(102),
+                Some(3),
+                Some(104),
+                Some(5),
+                Some(106),
+                Some(7)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_par_array_init_in_uses_pool() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        let array: [usize; 32] =
+            par_array_init_in(&pool, |_| rayon::current_thread_index().unwrap());
+        assert!(array.iter().all(|&thread| thread < 2));
+    }
+
+    #[test]
+    fn test_par_array_init_first_touch_works() {
+        let array: Box<[u64; 65536]> = par_array_init_first_touch(|i| (i * 3) as u64);
+        for (indx, i) in array.iter().enumerate() {
+            assert_eq!(*i, (indx * 3) as u64);
+        }
+    }
+
+    #[test]
+    fn test_par_array_init_first_touch_fills_each_page_on_one_thread() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+        // 12 byte elements don't evenly divide a page, and the box is only 4 byte aligned
+        let array: Box<[(u32, [u32; 2]); 8192]> = pool.install(|| {
+            par_array_init_first_touch(|i| {
+                // Slow enough that the other workers steal parts of the fill
+                std::thread::sleep(std::time::Duration::from_micros(20));
+                (i as u32, [rayon::current_thread_index().unwrap() as u32; 2])
+            })
+        });
+        let page_of = |i: usize| std::ptr::from_ref(&array[i]) as usize / PAGE_SIZE;
+        for i in 1..array.len() {
+            assert_eq!(array[i].0, i as u32);
+            if page_of(i) == page_of(i - 1) {
+                assert_eq!(
+                    array[i].1,
+                    array[i - 1].1,
+                    "page of element {} was split",
+                    i
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_par_array_init_builds_every_output_type() {
+        let array: [usize; 16] = par_array_init(|i| i * 2);
+        let boxed: Box<[usize; 16]> = par_array_init(|i| i * 2);
+        let arc: std::sync::Arc<[usize; 16]> = par_array_init(|i| i * 2);
+        for i in 0..16 {
+            assert_eq!(array[i], i * 2);
+            assert_eq!(boxed[i], i * 2);
+            assert_eq!(arc[i], i * 2);
+        }
+    }
+
+    #[test]
+    fn test_par_array_init_if_changed_counts_changes() {
+        let mut array: [usize; 8] = par_array_indices();
+        let changed =
+            par_array_init_if_changed(&mut array, |i| if i == 2 || i == 5 { 0 } else { i });
+        assert_eq!(changed, 2);
+        assert_eq!(array, [0, 1, 0, 3, 4, 0, 6, 7]);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_fill_span_records_len() {
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        struct LenVisitor(Option<u64>);
+        impl Visit for LenVisitor {
+            fn record_u64(&mut self, field: &Field, value: u64) {
+                if field.name() == "len" {
+                    self.0 = Some(value);
+                }
+            }
+            fn record_debug(&mut self, _: &Field, _: &dyn std::fmt::Debug) {}
+        }
+
+        type Spans = Arc<Mutex<Vec<(&'static str, Option<u64>)>>>;
+        struct SpanRecorder(Spans);
+        impl Subscriber for SpanRecorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                let mut visitor = LenVisitor(None);
+                span.record(&mut visitor);
+                let mut spans = self.0.lock().unwrap();
+                spans.push((span.metadata().name(), visitor.0));
+                Id::from_u64(spans.len() as u64)
+            }
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, _: &Event<'_>) {}
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let spans = Arc::new(Mutex::new(Vec::new()));
+        tracing::subscriber::with_default(SpanRecorder(spans.clone()), || {
+            let _array: [u32; 37] = par_array_init(|i| i as u32);
+            let _single: Option<[u32; 1]> = from_par_iter(vec![7]);
+            let _hand_rolled: [u32; 16] = par_array_init_in_scope(|i| i as u32);
+            // Built from a fill of the upper triangle and a fill of the rows, but still one fill
+            let _composite: [[u32; 3]; 3] = par_array_init_symmetric(|i, j| (i * j) as u32);
+        });
+        assert_eq!(
+            *spans.lock().unwrap(),
+            vec![
+                ("par_array_init", Some(37)),
+                ("par_array_init", Some(1)),
+                ("par_array_init", Some(16)),
+                ("par_array_init", Some(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_par_stack_array_init_works_under_limit() {
+        let array: [u32; 16] = par_stack_array_init(|i| i as u32);
+        assert_eq!(array[15], 15);
+    }
+
+    #[test]
+    fn test_par_array_init_broadcast_builds_state_per_thread() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let made = AtomicUsize::new(0);
+        let array: [usize; 256] = par_array_init_broadcast(
+            || {
+                made.fetch_add(1, Ordering::SeqCst);
+                vec![3; 256]
+            },
+            |table, i| table[i] * i,
+        );
+        for (indx, i) in array.iter().enumerate() {
+            assert_eq!(*i, indx * 3);
+        }
+        assert!(made.load(Ordering::SeqCst) <= rayon::current_num_threads());
+    }
+
+    #[test]
+    fn test_par_vec_init_works() {
+        let vec = par_vec_init(100, |i| i * i);
+        assert_eq!(vec, (0..100).map(|i| i * i).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn test_par_array_init_recurrence_computes_fibonacci() {
+        let array: [u64; 20] = par_array_init_recurrence(2, |previous, i| match previous {
+            [a, b] => a + b,
+            _ => i as u64,
+        });
+        assert_eq!(array[..6], [0, 1, 1, 2, 3, 5]);
+        assert_eq!(array[19], 4181);
+    }
+
+    #[test]
+    fn test_par_array_init_unique_accepts_distinct() {
+        let array: Result<[usize; 8], _> = par_array_init_unique(|i| i * 2);
+        assert_eq!(array.unwrap(), [0, 2, 4, 6, 8, 10, 12, 14]);
+    }
+
+    #[test]
+    fn test_par_array_init_unique_reports_duplicate() {
+        let result: Result<[usize; 8], _> = par_array_init_unique(|i| i % 4);
+        let error = result.unwrap_err();
+        let (first, second) = error.indices;
+        assert!(first < second);
+        assert_eq!(first % 4, second % 4);
+        assert_eq!(error.value, first % 4);
+    }
+
+    #[test]
+    fn test_par_array_init_unique_drops_elements_if_hash_panics() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+        #[derive(PartialEq, Eq)]
+        struct Tracked(usize);
+        impl std::hash::Hash for Tracked {
+            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                assert_ne!(self.0, 5, "can't hash 5");
+                self.0.hash(state);
+            }
+        }
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let result = std::panic::catch_unwind(|| {
+            let _: Result<[Tracked; 64], _> = par_array_init_unique(Tracked);
+        });
+        assert!(result.is_err());
+        assert_eq!(DROPS.load(Ordering::SeqCst), 64);
+    }
+
+    #[test]
+    fn test_par_array_init_works_with_saturating() {
+        use std::num::Saturating;
+        let array: [Saturating<u8>; 8] = par_array_init(|i| Saturating(250) + Saturating(i as u8));
+        assert_eq!(
+            array.map(|Saturating(x)| x),
+            [250, 251, 252, 253, 254, 255, 255, 255]
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_from_stream_works() {
+        let stream = futures::stream::iter(vec![1, 2, 3, 4]);
+        let array: Option<[i32; 4]> = futures::executor::block_on(from_stream(stream));
+        assert_eq!(array, Some([1, 2, 3, 4]));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_from_stream_fails_when_stream_is_short() {
+        let stream = futures::stream::iter(vec![1, 2, 3]);
+        let array: Option<[i32; 4]> = futures::executor::block_on(from_stream(stream));
+        assert_eq!(array, None);
+    }
+
+    #[test]
+    fn test_try_par_array_init_works() {
+        let array: Result<[usize; 8], ()> = try_par_array_init(|i| Ok(i + 1));
+        assert_eq!(array, Ok([1, 2, 3, 4, 5, 6, 7, 8]));
+        let array: Result<[usize; 8], usize> =
+            try_par_array_init(|i| if i >= 5 { Err(i) } else { Ok(i) });
+        assert_eq!(array, Err(5));
+    }
+
+    #[test]
+    fn test_happy_paths_never_hit_invariant_panics() {
+        let result = std::panic::catch_unwind(|| {
+            let _: [usize; 0] = par_array_init(|i| i);
+            let _: [usize; 1] = par_array_init(|i| i);
+            let _: [usize; 1000] = par_array_init(|i| i);
+            let _: std::sync::Arc<[usize; 100]> = par_array_init(|i| i);
+            let _: Option<[usize; 100]> = from_par_iter((0..100).into_par_iter());
+            let _: Result<[usize; 100], ()> = try_par_array_init(Ok);
+        });
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_par_array_init_task_count_is_bounded() {
+        use std::cell::Cell;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        thread_local!(static LAST: Cell<usize> = const { Cell::new(usize::MAX) });
+        // Each task fills a contiguous run on one thread, so a task starts whenever a thread's
+        // previous index isn't the one directly before this one
+        let tasks = AtomicUsize::new(0);
+        let array: Box<[usize; 1 << 16]> = par_array_init(|i| {
+            LAST.with(|last| {
+                if last.get().wrapping_add(1) != i {
+                    tasks.fetch_add(1, Ordering::Relaxed);
+                }
+                last.set(i);
+            });
+            i
+        });
+        for (indx, i) in array.iter().enumerate() {
+            assert_eq!(*i, indx);
+        }
+        let max_tasks = (1 << 16) / default_min_len::<usize>(1 << 16);
+        assert!(tasks.load(Ordering::Relaxed) <= max_tasks);
+    }
+
+    #[test]
+    fn test_par_array_init_chunked_works() {
+        let array: [usize; 100] = par_array_init_chunked(7, |i| i * 2);
+        for (indx, i) in array.iter().enumerate() {
+            assert_eq!(*i, indx * 2);
+        }
+    }
+
+    #[test]
+    fn test_from_par_iter_2d_is_row_major() {
+        let array: Option<[[u32; 4]; 3]> = from_par_iter_2d((0..12u32).into_par_iter());
+        assert_eq!(array, Some([[0, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11]]));
+        let short: Option<[[u32; 4]; 3]> = from_par_iter_2d((0..11u32).into_par_iter());
+        assert_eq!(short, None);
+    }
+
+    #[test]
+    fn test_par_array_init_abort_on_panic_aborts() {
+        const CHILD: &str = "PAR_ARRAY_INIT_ABORT_CHILD";
+        if std::env::var_os(CHILD).is_some() {
+            let _: [usize; 64] = par_array_init_abort_on_panic(|i| {
+                if i == 32 {
+                    panic!("boom");
+                }
+                i
+            });
+            return;
+        }
+        // Rerun just this test in a child process, which should abort rather than unwind
+        let status = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "test::test_par_array_init_abort_on_panic_aborts"])
+            .env(CHILD, "1")
+            .stderr(std::process::Stdio::null())
+            .status()
+            .unwrap();
+        assert!(!status.success());
+        // An unwinding test failure exits with 101
+        assert_ne!(status.code(), Some(101));
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            assert_eq!(status.signal(), Some(6));
+        }
+    }
+
+    #[test]
+    fn test_par_array_init_flat_map_works() {
+        let array: Option<[u32; 6]> = par_array_init_flat_map(3, |i| {
+            let i = i as u32;
+            [i * 10, i * 10 + 1]
+        });
+        assert_eq!(array, Some([0, 1, 10, 11, 20, 21]));
+    }
+
+    #[test]
+    fn test_par_array_init_flat_map_rejects_length_mismatch() {
+        let short: Option<[u32; 6]> = par_array_init_flat_map(2, |i| vec![i as u32; 2]);
+        assert_eq!(short, None);
+        let long: Option<[u32; 6]> = par_array_init_flat_map(4, |i| vec![i as u32; 2]);
+        assert_eq!(long, None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_par_array_init_cached_reads_from_cache() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let path =
+            std::env::temp_dir().join(format!("par_array_init_cache_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let runs = AtomicUsize::new(0);
+        let init = |i: usize| {
+            runs.fetch_add(1, Ordering::Relaxed);
+            i as u32 * 3
+        };
+
+        let first: [u32; 64] = par_array_init_cached(&path, init).unwrap();
+        assert_eq!(runs.load(Ordering::Relaxed), 64);
+        let second: [u32; 64] = par_array_init_cached(&path, init).unwrap();
+        assert_eq!(runs.load(Ordering::Relaxed), 64);
+        assert_eq!(first, second);
+
+        // A cache for a different length or element type is stale and gets recomputed
+        let longer: [u32; 65] = par_array_init_cached(&path, init).unwrap();
+        assert_eq!(runs.load(Ordering::Relaxed), 64 + 65);
+        assert_eq!(longer[64], 64 * 3);
+        let wider: [u64; 65] = par_array_init_cached(&path, |i| i as u64).unwrap();
+        assert_eq!(wider[64], 64);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_array_init_maybe_par_fills_rc_sequentially() {
+        use std::rc::Rc;
+
+        let array: [Rc<usize>; 16] = array_init_maybe_par(|i| Rc::new(i * 2));
+        for (indx, i) in array.iter().enumerate() {
+            assert_eq!(**i, indx * 2);
+        }
+        let array: [u32; 16] = array_init_maybe_par(|i| i as u32);
+        assert_eq!(array[15], 15);
+    }
+
+    #[test]
+    fn test_par_array_init_deterministic_pool_is_reproducible() {
+        let work = |i: usize| (0..16).fold(i as u64, |acc, x| acc.wrapping_mul(31).wrapping_add(x));
+        let first: [u64; 1024] = par_array_init_deterministic_pool(3, work).unwrap();
+        let second: [u64; 1024] = par_array_init_deterministic_pool(3, work).unwrap();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_par_array_init_repair_clamps_elements() {
+        let array: [usize; 8] = par_array_init_repair(|i| i, |_, elem: usize| elem.min(3));
+        assert_eq!(array, [0, 1, 2, 3, 3, 3, 3, 3]);
+    }
+
+    #[test]
+    fn test_from_par_iter_len_reports_actual_len() {
+        let array: Result<[i32; 10], usize> = from_par_iter_len(vec![1, 2, 3, 4]);
+        assert_eq!(array, Err(4));
+        let array: Result<[i32; 4], usize> = from_par_iter_len(vec![1, 2, 3, 4]);
+        assert_eq!(array, Ok([1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn test_par_array_init_in_scope_works_inside_scope() {
+        let mut array = [0usize; 100];
+        let mut other = 0;
+        rayon::scope(|s| {
+            s.spawn(|_| other = (0..100).sum());
+            array = par_array_init_in_scope(|i| i * 3);
+        });
+        for (indx, i) in array.iter().enumerate() {
+            assert_eq!(*i, indx * 3);
+        }
+        assert_eq!(other, 4950);
+    }
+
+    #[test]
+    fn test_par_array_init_in_scope_drops_elements_on_panic() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CREATED: AtomicUsize = AtomicUsize::new(0);
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+        struct Counted;
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let result = std::panic::catch_unwind(|| {
+            rayon::scope(|_| {
+                let _: [Counted; 64] = par_array_init_in_scope(|i| {
+                    if i == 40 {
+                        panic!("boom");
+                    }
+                    CREATED.fetch_add(1, Ordering::SeqCst);
+                    Counted
+                });
+            })
+        });
+        assert!(result.is_err());
+        assert_eq!(CREATED.load(Ordering::SeqCst), DROPS.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_par_array_init_unfold_matches_sequential_lcg() {
+        const A: u32 = 1_664_525;
+        // A multiplicative LCG, x_i = seed * A^i, can jump straight to any index
+        let array: [u32; 16] = par_array_init_unfold(12345u32, |seed, i| {
+            seed.wrapping_mul(A.wrapping_pow(i as u32))
+        });
+        let mut x = 12345u32;
+        for elem in array.iter() {
+            assert_eq!(*elem, x);
+            x = x.wrapping_mul(A);
+        }
+    }
+
+    #[test]
+    fn test_par_array_try_map_reports_invalid_byte() {
+        use std::convert::TryFrom;
+
+        #[derive(Debug, PartialEq)]
+        enum Op {
+            Push,
+            Pop,
+        }
+        impl TryFrom<u8> for Op {
+            type Error = u8;
+            fn try_from(byte: u8) -> Result<Self, u8> {
+                match byte {
+                    0 => Ok(Op::Push),
+                    1 => Ok(Op::Pop),
+                    _ => Err(byte),
+                }
+            }
+        }
+
+        assert_eq!(
+            par_array_try_map([0u8, 1, 1, 0], Op::try_from),
+            Ok([Op::Push, Op::Pop, Op::Pop, Op::Push])
+        );
+        assert_eq!(par_array_try_map([0u8, 1, 7, 0], Op::try_from), Err(7));
+    }
+
+    #[test]
+    fn test_par_array_init_scratch_reuses_buffer() {
+        let array: [u64; 64] = par_array_init_scratch(Vec::<u8>::new, |scratch, i| {
+            scratch.clear();
+            scratch.extend(i.to_string().bytes());
+            scratch.iter().map(|b| u64::from(b - b'0')).sum()
+        });
+        for (indx, i) in array.iter().enumerate() {
+            let digits: u64 = indx.to_string().bytes().map(|b| u64::from(b - b'0')).sum();
+            assert_eq!(*i, digits);
+        }
+    }
+
+    #[test]
+    fn test_par_boxed_slice_init_works() {
+        let slice: Box<[usize]> = par_boxed_slice_init(50, |i| i * i);
+        assert_eq!(slice.len(), 50);
+        for (indx, i) in slice.iter().enumerate() {
+            assert_eq!(*i, indx * indx);
+        }
+    }
+
+    #[test]
+    fn test_from_par_iters_halves_works() {
+        let array: Option<[u32; 6]> = from_par_iters_halves(vec![1, 2, 3], vec![4, 5, 6]);
+        assert_eq!(array, Some([1, 2, 3, 4, 5, 6]));
+    }
+
+    #[test]
+    fn test_from_par_iters_halves_rejects_bad_lengths() {
+        let short: Option<[u32; 6]> = from_par_iters_halves(vec![1, 2], vec![4, 5, 6]);
+        assert_eq!(short, None);
+        let odd: Option<[u32; 7]> = from_par_iters_halves(vec![1, 2, 3, 4], vec![4, 5, 6, 7]);
+        assert_eq!(odd, None);
+    }
+
+    #[test]
+    fn test_par_array_init_typed_index_works() {
+        let array: [usize; 8] =
+            par_array_init_typed_index(|i| i.get() * 10 + i.saturating_prev().get());
+        assert_eq!(array, [0, 10, 21, 32, 43, 54, 65, 76]);
+    }
+
+    #[test]
+    fn test_finish_par_init_only_fills_unfilled_slots() {
+        let mut array = [MaybeUninit::<u32>::uninit(); 8];
+        let mut filled = [false; 8];
+        for i in (0..8).step_by(2) {
+            array[i].write(100 + i as u32);
+            filled[i] = true;
+        }
+        let array = unsafe { finish_par_init(array, &filled, |i| i as u32) };
+        assert_eq!(array, [100, 1, 102, 3, 104, 5, 106, 7]);
+    }
+
+    #[test]
+    fn test_par_array_init_works_with_phantom_data_wrappers() {
+        use std::marker::PhantomData;
+
+        #[derive(Debug, PartialEq)]
+        struct Meters(f64, PhantomData<()>);
+
+        let array: [Meters; 8] = par_array_init(|i| Meters(i as f64 * 0.5, PhantomData));
+        for (indx, meters) in array.iter().enumerate() {
+            assert_eq!(*meters, Meters(indx as f64 * 0.5, PhantomData));
+        }
+    }
+
+    #[test]
+    fn test_par_array_init_with_reduce_sums_elements() {
+        let (array, sum): ([u32; 8], u32) =
+            par_array_init_with_reduce(|i| i as u32, 0, |a, b| a + b);
+        assert_eq!(array, [0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(sum, 28);
+    }
+
+    #[test]
+    fn test_par_array_init_with_reduce_is_deterministic() {
+        let init = |i: usize| 1.0 / (i as f64 + 1.0);
+        let sums: Vec<f64> = [1, 2, 3]
+            .iter()
+            .map(|&threads| {
+                let pool = rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .unwrap();
+                pool.install(|| {
+                    let (_, sum): ([f64; 10_000], f64) =
+                        par_array_init_with_reduce(init, 0.0, |a, b| a + b);
+                    sum
+                })
+            })
+            .collect();
+        assert!(sums.iter().all(|sum| sum.to_bits() == sums[0].to_bits()));
+    }
+
+    #[test]
+    fn test_try_par_array_init_catch_returns_error() {
+        let array: Result<[usize; 8], InitFailure<usize>> =
+            try_par_array_init_catch(|i| if i == 3 { Err(i) } else { Ok(i) });
+        assert!(matches!(array, Err(InitFailure::Error(3))));
+    }
+
+    #[test]
+    fn test_try_par_array_init_catch_returns_panic() {
+        let array: Result<[usize; 8], InitFailure<usize>> = try_par_array_init_catch(|i| {
+            if i == 3 {
+                panic!("boom");
+            }
+            Ok(i)
+        });
+        match array {
+            Err(InitFailure::Panic(payload)) => assert_eq!(payload.downcast_ref(), Some(&"boom")),
+            other => panic!("expected a panic, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_par_array_init_3d_indexes_each_axis() {
+        let array: [[[u8; 2]; 2]; 2] = par_array_init_3d(|w, h, d| (w * 4 + h * 2 + d) as u8);
+        assert_eq!(array, [[[0, 1], [2, 3]], [[4, 5], [6, 7]]]);
+        let array: [[[u8; 3]; 0]; 2] = par_array_init_3d(|_, _, _| unreachable!());
+        assert_eq!(array.len(), 2);
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn test_par_fill_bytes_fills_f32s() {
+        // Backed by u32s so the bytes are aligned for f32
+        let mut backing = vec![0u32; 16];
+        let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut backing);
+        assert_eq!(par_fill_bytes(bytes, |i| i as f32 * 1.5), Ok(()));
+        let floats: &[f32] = bytemuck::cast_slice(bytes);
+        for (indx, f) in floats.iter().enumerate() {
+            assert_eq!(*f, indx as f32 * 1.5);
+        }
+
+        assert_eq!(
+            par_fill_bytes(&mut bytes[..63], |i| i as f32),
+            Err(FillError::Length {
+                len: 63,
+                elem_size: 4
+            })
+        );
+        assert_eq!(
+            par_fill_bytes(&mut bytes[1..5], |i| i as f32),
+            Err(FillError::Misaligned { align: 4 })
+        );
+    }
+
+    #[test]
+    fn test_par_array_init_non_blocking_works_on_busy_pool() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        pool.scope(|s| {
+            // Leave tasks queued on this worker so the pool looks busy
+            for _ in 0..8 {
+                s.spawn(|_| std::thread::sleep(std::time::Duration::from_millis(1)));
+            }
+            let here = rayon::current_thread_index();
+            let array: [(usize, Option<usize>); 64] =
+                par_array_init_non_blocking(|i| (i * 2, rayon::current_thread_index()));
+            for (indx, (i, thread)) in array.iter().enumerate() {
+                assert_eq!(*i, indx * 2);
+                assert_eq!(*thread, here);
+            }
+        });
+        let array: [usize; 64] = par_array_init_non_blocking(|i| i * 2);
+        assert_eq!(array[63], 126);
+    }
+
+    #[test]
+    fn test_par_array_init_like_copies_template_len() {
+        let template = vec![7u32; 5];
+        let array: [u32; 5] = par_array_init_like(&template, |i| i as u32 + template[i]);
+        assert_eq!(array, [7, 8, 9, 10, 11]);
+    }
+
+    #[test]
+    #[should_panic(expected = "different length")]
+    fn test_par_array_init_like_rejects_wrong_len() {
+        let template = [0u32; 4];
+        let _: [u32; 5] = par_array_init_like(&template, |i| i as u32);
+    }
+
+    #[cfg(feature = "arrayvec")]
+    #[test]
+    fn test_from_par_iter_arrayvec_keeps_available_prefix() {
+        let vec: arrayvec::ArrayVec<u32, 5> = from_par_iter_arrayvec(vec![1, 2, 3]);
+        assert_eq!(vec.len(), 3);
+        assert_eq!(vec.as_slice(), &[1, 2, 3]);
+        let full: arrayvec::ArrayVec<u32, 2> = from_par_iter_arrayvec(vec![1, 2, 3]);
+        assert_eq!(full.as_slice(), &[1, 2]);
+    }
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_from_par_iter_2d_rejects_overflowing_len() {
+        // 2^16 * 2^16 wraps to 0 on 32 bit targets
+        let array: Option<[[(); 1 << 16]; 1 << 16]> =
+            from_par_iter_2d(rayon::iter::repeat_n((), 0));
+        assert_eq!(array, None);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_from_par_iter_2d_rejects_overflowing_len() {
+        // 2^32 * 2^32 wraps to 0 on 64 bit targets
+        let array: Option<[[(); 1 << 32]; 1 << 32]> =
+            from_par_iter_2d(rayon::iter::repeat_n((), 0));
+        assert_eq!(array, None);
+    }
+
+    #[test]
+    fn test_par_overwrite_with_hands_back_old_elements() {
+        let mut array = [10u32, 20, 30, 40];
+        let displaced = std::sync::Mutex::new(Vec::new());
+        par_overwrite_with(
+            &mut array,
+            |i| i as u32,
+            |old| displaced.lock().unwrap().push(old),
+        );
+        assert_eq!(array, [0, 1, 2, 3]);
+        let mut displaced = displaced.into_inner().unwrap();
+        displaced.sort_unstable();
+        assert_eq!(displaced, vec![10, 20, 30, 40]);
+
+        par_overwrite(&mut array, |i| i as u32 * 2);
+        assert_eq!(array, [0, 2, 4, 6]);
+    }
+
+    #[cfg(feature = "bitvec")]
+    #[test]
+    fn test_par_bitset_init_packs_even_bits() {
+        let bits: bitvec::BitArr!(for 64) = par_bitset_init(|i| i % 2 == 0);
+        assert_eq!(bits.len(), 64);
+        for (indx, bit) in bits.iter().enumerate() {
+            assert_eq!(*bit, indx % 2 == 0);
+        }
+        assert_eq!(bits.count_ones(), 32);
+    }
+
+    #[cfg(feature = "debug-determinism")]
+    #[test]
+    fn test_par_array_init_check_determinism_accepts_pure_closure() {
+        let array: [usize; 256] = par_array_init_check_determinism(|i| i * i);
+        assert_eq!(array[255], 255 * 255);
+    }
+
+    #[cfg(feature = "debug-determinism")]
+    #[test]
+    #[should_panic(expected = "initializer is not deterministic")]
+    fn test_par_array_init_check_determinism_flags_shared_counter() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let counter = AtomicUsize::new(0);
+        let _: [usize; 256] =
+            par_array_init_check_determinism(|_| counter.fetch_add(1, Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_par_array_init_from_table_clones_strings() {
+        const NAMES: [&str; 4] = ["zero", "one", "two", "three"];
+        let table: [String; 4] = NAMES.map(String::from);
+        let array = par_array_init_from_table(&table);
+        assert_eq!(array, table);
+        assert_ne!(array[0].as_ptr(), table[0].as_ptr());
+    }
+
+    #[test]
+    fn test_par_array_from_slice_or_init_clones_matching_slice() {
+        let src: Vec<u32> = (10..18).collect();
+        let array: [u32; 8] = par_array_from_slice_or_init(Some(&src), |_| unreachable!());
+        assert_eq!(array, [10, 11, 12, 13, 14, 15, 16, 17]);
+    }
+
+    #[test]
+    fn test_par_array_from_slice_or_init_computes_otherwise() {
+        let array: [u32; 8] = par_array_from_slice_or_init(None, |i| i as u32);
+        assert_eq!(array, [0, 1, 2, 3, 4, 5, 6, 7]);
+        let short = [1u32; 3];
+        let array: [u32; 8] = par_array_from_slice_or_init(Some(&short), |i| i as u32);
+        assert_eq!(array, [0, 1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[cfg(any(
+        feature = "deterministic",
+        all(target_arch = "wasm32", not(target_feature = "atomics"))
+    ))]
+    #[test]
+    fn test_sequential_fills_call_in_order() {
+        let caller = std::thread::current().id();
+        let order = std::sync::Mutex::new(Vec::new());
+        let record = |i: usize| {
+            assert_eq!(std::thread::current().id(), caller);
+            order.lock().unwrap().push(i);
+        };
+        let take_order = || std::mem::take(&mut *order.lock().unwrap());
+
+        let array: [usize; 16] = par_array_init(|i| {
+            record(i);
+            i * 2
+        });
+        assert_eq!(array[15], 30);
+        assert_eq!(take_order(), (0..16).collect::<Vec<_>>());
+
+        let array: Result<[usize; 16], usize> = try_par_array_init(|i| {
+            record(i);
+            if i == 9 {
+                Err(i)
+            } else {
+                Ok(i)
+            }
+        });
+        assert_eq!(array, Err(9));
+        // Nothing after the first error is started
+        assert_eq!(take_order(), (0..10).collect::<Vec<_>>());
+
+        let array: Option<[usize; 16]> = from_par_iter((0..16).into_par_iter().rev().map(|i| {
+            record(i);
+            i
+        }));
+        assert_eq!(array.unwrap()[0], 15);
+        assert_eq!(take_order(), (0..16).rev().collect::<Vec<_>>());
+
+        let array: [usize; 16] = par_array_init_pinned(|i| {
+            record(i);
+            i
+        });
+        assert_eq!(array[15], 15);
+        assert_eq!(take_order(), (0..16).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_par_array_init_with_chunk_hook_partitions_indices() {
+        let ranges = std::sync::Mutex::new(Vec::new());
+        let array: [usize; 1000] =
+            par_array_init_with_chunk_hook(|i| i, |range| ranges.lock().unwrap().push(range));
+        for (indx, i) in array.iter().enumerate() {
+            assert_eq!(*i, indx);
+        }
+        let mut ranges = ranges.into_inner().unwrap();
+        ranges.sort_by_key(|range| range.start);
+        let mut next = 0;
+        for range in ranges {
+            assert_eq!(range.start, next);
+            assert!(range.end > range.start);
+            next = range.end;
+        }
+        assert_eq!(next, 1000);
+    }
+
+    #[test]
+    fn test_array_init_rc_holds_rc_elements() {
+        use std::rc::Rc;
+
+        let shared = Rc::new(7u32);
+        let array: Rc<[Rc<u32>; 4]> = array_init_rc(|i| {
+            if i == 0 {
+                Rc::clone(&shared)
+            } else {
+                Rc::new(i as u32)
+            }
+        });
+        assert_eq!(*array[0], 7);
+        assert_eq!(*array[3], 3);
+        assert_eq!(Rc::strong_count(&shared), 2);
+    }
+
+    #[test]
+    fn test_par_array_init_into_converts_strs() {
+        const NAMES: [&str; 4] = ["a", "b", "c", "d"];
+        let array = par_array_init_into::<[String; 4], _, _>(|i| NAMES[i]);
+        assert_eq!(array, ["a", "b", "c", "d"].map(String::from));
+    }
+
+    #[test]
+    fn test_par_array_init_inside_nested_joins() {
+        fn fill_nested(depth: usize) -> u64 {
+            if depth == 0 {
+                let array: Box<[u64; 4096]> = par_array_init(|i| i as u64);
+                // Fills started from inside another fill's closure
+                let nested: [u64; 8] =
+                    par_array_init(|i| par_array_init::<[u64; 16], _>(|j| (i * j) as u64)[15]);
+                return array.iter().sum::<u64>() + nested.iter().sum::<u64>();
+            }
+            let (a, b) = rayon::join(|| fill_nested(depth - 1), || fill_nested(depth - 1));
+            a + b
+        }
+
+        let expected = 4095 * 4096 / 2 + (0..8).map(|i| i * 15).sum::<u64>();
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+        assert_eq!(pool.install(|| fill_nested(6)), expected << 6);
+    }
+
+    #[test]
+    fn test_as_ffi_slice_is_contiguous() {
+        assert_eq!(size_of::<[u32; 4]>(), 4 * size_of::<u32>());
+        let array: [u32; 4] = par_array_init(|i| i as u32);
+        let slice = array.as_ffi_slice();
+        assert_eq!(slice.as_ptr(), array.as_ptr());
+        assert_eq!(size_of_val(slice), size_of_val(&array));
+        assert_eq!(slice, &[0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_par_array_init_sparse_applies_overrides() {
+        let overrides = std::collections::HashMap::from([(1, 100), (3, 300)]);
+        let array: Result<[u32; 5], _> =
+            par_array_init_sparse(&overrides, OutOfRange::Reject, |i| i as u32);
+        assert_eq!(array, Ok([0, 100, 2, 300, 4]));
+    }
+
+    #[test]
+    fn test_par_array_init_sparse_handles_out_of_range_keys() {
+        let overrides = std::collections::HashMap::from([(1, 100), (7, 700)]);
+        let array: Result<[u32; 5], _> =
+            par_array_init_sparse(&overrides, OutOfRange::Ignore, |i| i as u32);
+        assert_eq!(array, Ok([0, 100, 2, 3, 4]));
+        let array: Result<[u32; 5], _> =
+            par_array_init_sparse(&overrides, OutOfRange::Reject, |i| i as u32);
+        assert_eq!(array, Err(OverrideError { index: 7, len: 5 }));
+    }
+
+    #[test]
+    fn test_copy_and_move_elements_fill_the_same() {
+        #[derive(Debug, PartialEq)]
+        struct Moved(usize);
+        impl Drop for Moved {
+            fn drop(&mut self) {}
+        }
+
+        assert!(!std::mem::needs_drop::<usize>());
+        assert!(std::mem::needs_drop::<Moved>());
+        let copied: [usize; 1000] = par_array_init(|i| i * 3);
+        let moved: [Moved; 1000] = par_array_init(|i| Moved(i * 3));
+        assert!(copied.iter().zip(moved.iter()).all(|(c, m)| *c == m.0));
+    }
+
+    #[test]
+    fn test_par_array_init_deadline() {
+        use std::time::{Duration, Instant};
+
+        let arr: Result<[usize; 8], _> =
+            par_array_init_deadline(Instant::now() + Duration::from_secs(60), |i| i);
+        assert_eq!(arr.ok(), Some([0, 1, 2, 3, 4, 5, 6, 7]));
+
+        let deadline = Instant::now() + Duration::from_millis(20);
+        let partial = par_array_init_deadline::<[usize; 64], _>(deadline, |i| {
+            std::thread::sleep(Duration::from_millis(10));
+            i
+        })
+        .unwrap_err();
+        assert!(partial.len() < 64);
+        assert!(partial.iter().all(|(i, elem)| i == elem));
+    }
+
+    #[test]
+    fn test_par_array_init_opt() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let arr: Option<[usize; 8]> = par_array_init_opt(Some);
+        assert_eq!(arr, Some([0, 1, 2, 3, 4, 5, 6, 7]));
+        let arr: Option<[usize; 8]> = par_array_init_opt(|i| (i != 3).then_some(i));
+        assert_eq!(arr, None);
+
+        static CREATED: AtomicUsize = AtomicUsize::new(0);
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+        struct Tracked;
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let arr: Option<[Tracked; 256]> = par_array_init_opt(|i| {
+            (i != 100).then(|| {
+                CREATED.fetch_add(1, Ordering::SeqCst);
+                Tracked
+            })
+        });
+        assert!(arr.is_none());
+        assert_eq!(DROPS.load(Ordering::SeqCst), CREATED.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_par_array_init_audited() {
+        let (arr, log): ([String; 256], _) = par_array_init_audited(|i| i.to_string());
+        assert_eq!(log.len(), 256);
+        assert_eq!(arr[255], "255");
+        let (_, again): ([String; 256], _) = par_array_init_audited(|i| i.to_string());
+        assert_eq!(log, again);
+        assert_ne!(log[0], log[1]);
+
+        // Known FNV-1a hashes of the little endian u64 bytes, the same for every integer width
+        let (_, log): ([usize; 301], _) = par_array_init_audited(|i| i);
+        let (_, narrow): ([u16; 301], _) = par_array_init_audited(|i| i as u16);
+        assert_eq!(log[7], 0x4bd7_a317_074c_5b62);
+        assert_eq!(log[300], 0x8007_b7aa_a41a_4b4e);
+        assert_eq!(log, narrow);
+    }
+
+    #[test]
+    fn test_par_array_init_reuse_keeps_capacity() {
+        let mut buf = Vec::new();
+        assert_eq!(
+            par_array_init_reuse(&mut buf, 1000, |i| i.to_string())[999],
+            "999"
+        );
+        let ptr = buf.as_ptr();
+        let capacity = buf.capacity();
+        for round in 1..3 {
+            let elems = par_array_init_reuse(&mut buf, 1000, |i| (i * round).to_string());
+            assert_eq!(elems[999], (999 * round).to_string());
+            assert_eq!(buf.as_ptr(), ptr);
+            assert_eq!(buf.capacity(), capacity);
+        }
+    }
+
+    #[test]
+    fn test_par_array_init_sorted() {
+        let arr: [i32; 8] = par_array_init_sorted(|i| 7 - i as i32, i32::cmp);
+        assert_eq!(arr, [0, 1, 2, 3, 4, 5, 6, 7]);
+        let arr: [i32; 8] = par_array_init_sorted(|i| i as i32, |a: &i32, b: &i32| b.cmp(a));
+        assert_eq!(arr, [7, 6, 5, 4, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn test_par_init_into_each_target() {
+        let mut array = [0usize; 100];
+        let mut vec = vec![String::new(); 100];
+        let mut boxed = vec![0u64; 100].into_boxed_slice();
+        par_init_into(&mut array, |i| i * 2);
+        par_init_into(&mut vec, |i| i.to_string());
+        par_init_into(&mut boxed, |i| i as u64 + 1);
+        assert_eq!(ParIndexedWrite::len(&array), 100);
+        assert_eq!(ParIndexedWrite::len(&vec), 100);
+        assert_eq!(ParIndexedWrite::len(&boxed), 100);
+        for i in 0..100 {
+            assert_eq!(array[i], i * 2);
+            assert_eq!(vec[i], i.to_string());
+            assert_eq!(boxed[i], i as u64 + 1);
+        }
+    }
+
+    #[test]
+    fn test_par_array_init_validate_streaming() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let arr: Result<[usize; 8], _> = par_array_init_validate_streaming(|i| i, |_, _| true);
+        assert_eq!(arr, Ok([0, 1, 2, 3, 4, 5, 6, 7]));
+
+        static CREATED: AtomicUsize = AtomicUsize::new(0);
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+        struct Tracked(usize);
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let arr: Result<[Tracked; 1024], _> = par_array_init_validate_streaming(
+            |i| {
+                CREATED.fetch_add(1, Ordering::SeqCst);
+                Tracked(i)
+            },
+            |_, elem: &Tracked| elem.0 != 3,
+        );
+        assert_eq!(arr.err(), Some(3));
+        assert!(CREATED.load(Ordering::SeqCst) < 1024);
+        assert_eq!(DROPS.load(Ordering::SeqCst), CREATED.load(Ordering::SeqCst));
+    }
+
+    #[cfg(feature = "bitvec")]
+    #[test]
+    fn test_par_array_init_timeout_marked() {
+        use std::time::{Duration, Instant};
+
+        let (arr, marks): ([usize; 64], bitvec::BitArr!(for 64)) =
+            par_array_init_timeout_marked(Instant::now() + Duration::from_secs(60), |i| i, |_| 0);
+        assert_eq!(arr, par_array_indices());
+        assert!(marks.not_any());
+
+        let deadline = Instant::now() + Duration::from_millis(20);
+        let (arr, marks): ([usize; 64], bitvec::BitArr!(for 64)) = par_array_init_timeout_marked(
+            deadline,
+            |i| {
+                std::thread::sleep(Duration::from_millis(5));
+                i + 1
+            },
+            |_| 0,
+        );
+        assert!(marks.any());
+        for (i, elem) in arr.iter().enumerate() {
+            assert_eq!(marks[i], *elem == 0);
+        }
+    }
+
+    #[test]
+    fn test_single_element_skips_the_thread_pool() {
+        assert!(rayon::current_thread_index().is_none());
+        let arr: [u8; 1] = par_array_init(|i| {
+            assert!(rayon::current_thread_index().is_none());
+            i as u8 + 7
+        });
+        assert_eq!(arr, [7]);
+        let arr: Option<[u8; 1]> = from_par_iter(
+            (0..4u8)
+                .into_par_iter()
+                .inspect(|_| assert!(rayon::current_thread_index().is_none())),
+        );
+        assert_eq!(arr, Some([0]));
+    }
+
+    #[test]
+    fn test_par_array_init_by_variant() {
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        enum Suit {
+            Clubs,
+            Diamonds,
+            Hearts,
+            Spades,
+        }
+        impl DiscriminantIndexed for Suit {
+            const COUNT: usize = 4;
+            fn from_index(index: usize) -> Self {
+                [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades][index]
+            }
+        }
+
+        let names: [String; Suit::COUNT] =
+            par_array_init_by_variant(|suit: Suit| format!("{:?}", suit));
+        assert_eq!(names, ["Clubs", "Diamonds", "Hearts", "Spades"]);
+        let red: [bool; 4] =
+            par_array_init_by_variant(|suit: Suit| suit == Suit::Diamonds || suit == Suit::Hearts);
+        assert_eq!(red, [false, true, true, false]);
+    }
+
+    #[test]
+    fn test_par_array_init_prefetch() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let table: Vec<u64> = (0..4096).map(|i| i * 3).collect();
+        let arr: Box<[u64; 4096]> = Box::new(par_array_init_prefetch(|i| table[i] + 1, |_| {}));
+        assert!(arr
+            .iter()
+            .enumerate()
+            .all(|(i, elem)| *elem == i as u64 * 3 + 1));
+
+        let prefetched = AtomicUsize::new(0);
+        let _: [u64; 64] = par_array_init_prefetch(
+            |i| i as u64,
+            |ahead| {
+                assert!((PREFETCH_DISTANCE..64).contains(&ahead));
+                prefetched.fetch_add(1, Ordering::Relaxed);
+            },
+        );
+        assert_eq!(prefetched.load(Ordering::Relaxed), 64 - PREFETCH_DISTANCE);
+    }
+
+    #[test]
+    fn test_par_array_init_timed() {
+        use std::time::Duration;
+
+        let (arr, durations): ([usize; 16], _) = par_array_init_timed(|i| {
+            if i == 5 {
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            i
+        });
+        assert_eq!(arr, par_array_indices());
+        assert_eq!(durations.len(), 16);
+        assert!(durations[5] >= Duration::from_millis(20));
+        assert!(durations
+            .iter()
+            .enumerate()
+            .all(|(i, elapsed)| i == 5 || *elapsed < durations[5]));
+    }
+
+    #[test]
+    fn test_par_array_init_upgrade() {
+        use std::sync::Arc;
+
+        let live = [Arc::new(0), Arc::new(2)];
+        let weaks = [
+            Arc::downgrade(&live[0]),
+            Arc::downgrade(&Arc::new(1)),
+            Arc::downgrade(&live[1]),
+            std::sync::Weak::new(),
+        ];
+        let arcs = par_array_init_upgrade(&weaks, |i| Arc::new(i * 10));
+        assert_eq!(arcs.map(|arc| *arc), [0, 10, 2, 30]);
+        assert_eq!(Arc::strong_count(&live[0]), 1);
+        let arcs = par_array_init_upgrade(&weaks, |i| Arc::new(i * 10));
+        assert!(Arc::ptr_eq(&arcs[2], &live[1]));
+    }
+
+    #[test]
+    fn test_par_array_init_iter() {
+        let evens = par_array_init_iter::<_, _, 1000>(|i| i * 3)
+            .filter(|elem| elem % 2 == 0)
+            .count();
+        assert_eq!(evens, 500);
+        let iter = par_array_init_iter::<_, _, 8>(|i| i.to_string());
+        assert_eq!(iter.len(), 8);
+        assert_eq!(
+            iter.collect_array(),
+            ["0", "1", "2", "3", "4", "5", "6", "7"]
+        );
+    }
+
+    #[test]
+    fn test_par_reinit_slice_drops_old_elements_once() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+        struct Tracked(String);
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let mut buf: Vec<Tracked> = (0..100).map(|i| Tracked(i.to_string())).collect();
+        par_reinit_slice(&mut buf[..], |i| Tracked((i * 2).to_string()));
+        assert_eq!(DROPS.load(Ordering::SeqCst), 100);
+        assert!(buf
+            .iter()
+            .enumerate()
+            .all(|(i, elem)| elem.0 == (i * 2).to_string()));
+
+        let mut strings = vec![String::from("old"); 4];
+        par_reinit_slice(&mut strings, |i| i.to_string());
+        assert_eq!(strings, ["0", "1", "2", "3"]);
+    }
+
+    #[test]
+    fn test_par_array_init_atomics() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let atomics = par_array_init::<[AtomicU32; 16], _>(|i| AtomicU32::new(i as u32));
+        atomics[3].fetch_add(10, Ordering::Relaxed);
+        for (i, atomic) in atomics.iter().enumerate() {
+            let expected = if i == 3 { 13 } else { i as u32 };
+            assert_eq!(atomic.load(Ordering::Relaxed), expected);
+        }
+    }
+
+    #[test]
+    fn test_par_array_init_encoded() {
+        let (arr, bytes): ([u32; 4], _) = par_array_init_encoded(
+            |i| 0x0102_0300 + i as u32,
+            |elem: &u32| elem.to_le_bytes().to_vec(),
+        );
+        assert_eq!(arr, [0x0102_0300, 0x0102_0301, 0x0102_0302, 0x0102_0303]);
+        assert_eq!(
+            bytes,
+            [[0, 3, 2, 1], [1, 3, 2, 1], [2, 3, 2, 1], [3, 3, 2, 1]]
+        );
+    }
+
+    #[test]
+    fn test_try_par_array_init_with_question_mark() {
+        fn parse_all(words: &[&str; 4]) -> anyhow::Result<[u32; 4]> {
+            let parsed = try_par_array_init(|i| -> anyhow::Result<u32> {
+                let n: u32 = words[i].parse()?;
+                Ok(n * 2)
+            })?;
+            Ok(parsed)
+        }
+
+        assert_eq!(parse_all(&["1", "2", "3", "4"]).unwrap(), [2, 4, 6, 8]);
+        let error = parse_all(&["1", "two", "3", "4"]).unwrap_err();
+        assert!(error.downcast_ref::<std::num::ParseIntError>().is_some());
+
+        let error: anyhow::Error = OverrideError { index: 9, len: 4 }.into();
+        assert!(error.to_string().contains('9'));
+        let failure: InitFailure<&str> = "bad".into();
+        assert!(matches!(failure, InitFailure::Error("bad")));
+    }
+
+    #[test]
+    fn test_par_array_init_rotated() {
+        let ring: [u32; 5] = par_array_init_rotated(2, |i| i as u32 * 10);
+        assert_eq!(ring, [30, 40, 0, 10, 20]);
+        let wrapped: [u32; 5] = par_array_init_rotated(7, |i| i as u32 * 10);
+        assert_eq!(wrapped, ring);
+        let unrotated: [u32; 5] = par_array_init_rotated(5, |i| i as u32);
+        assert_eq!(unrotated, [0, 1, 2, 3, 4]);
+        let empty: [u32; 0] = par_array_init_rotated(3, |i| i as u32);
+        assert_eq!(empty, []);
+    }
+
+    #[test]
+    fn test_par_array_durations() {
+        use std::time::{Duration, Instant};
+
+        let schedule: [Duration; 8] = par_array_durations(|i| Duration::from_millis(i as u64));
+        for (i, delay) in schedule.iter().enumerate() {
+            assert_eq!(*delay, Duration::from_millis(i as u64));
+        }
+        let start = Instant::now();
+        let deadlines: [Instant; 8] = par_array_init(|i| start + schedule[i]);
+        assert!(deadlines.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_par_array_init_with_consumes_in_place() {
+        let checksum = par_array_init_with::<[u64; 4096], _, _, _>(
+            |i| i as u64,
+            |array| array.iter().sum::<u64>(),
+        );
+        assert_eq!(checksum, 4095 * 4096 / 2);
+
+        let shared = std::sync::Arc::new(());
+        let len = par_array_init_with::<[std::sync::Arc<()>; 64], _, _, _>(
+            |_| shared.clone(),
+            |array| {
+                assert_eq!(std::sync::Arc::strong_count(&shared), 65);
+                array.len()
+            },
+        );
+        assert_eq!(len, 64);
+        assert_eq!(std::sync::Arc::strong_count(&shared), 1);
+    }
+
+    #[test]
+    fn test_par_array_init_offset() {
+        let one_based: [usize; 5] = par_array_init_offset(1, |i| i as usize);
+        assert_eq!(one_based, [1, 2, 3, 4, 5]);
+        let shifted: [i64; 5] = par_array_init_offset(-2, |i| i as i64 * 10);
+        assert_eq!(shifted, [-20, -10, 0, 10, 20]);
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn test_par_array_from_mmap() {
+        // Backed by u32s so the bytes are aligned like an mmap'd region would be
+        let words: Vec<u32> = (0..17).map(|i| i * 7).collect();
+        let bytes: &[u8] = bytemuck::cast_slice(&words);
+
+        let arr: Option<[u32; 16]> = par_array_from_mmap(&bytes[..64]);
+        assert_eq!(arr, Some(par_array_init(|i| i as u32 * 7)));
+        assert_eq!(par_array_from_mmap::<[u32; 16], _>(bytes), None);
+        assert_eq!(par_array_from_mmap::<[u32; 16], _>(&bytes[1..65]), None);
+    }
+
+    #[test]
+    fn test_par_array_init_retry() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let attempts: [AtomicUsize; 16] = par_array_init(|_| AtomicUsize::new(0));
+        let flaky = |i: usize| {
+            let attempt = attempts[i].fetch_add(1, Ordering::SeqCst);
+            if attempt < 2 {
+                Err(i)
+            } else {
+                Ok(i * 2)
+            }
+        };
+        let arr: Result<[usize; 16], _> = par_array_init_retry(2, flaky);
+        assert_eq!(arr, Ok(par_array_init(|i| i * 2)));
+        assert!(attempts.iter().all(|n| n.load(Ordering::SeqCst) == 3));
+
+        attempts.iter().for_each(|n| n.store(0, Ordering::SeqCst));
+        let arr: Result<[usize; 16], _> = par_array_init_retry(1, flaky);
+        assert_eq!(arr, Err(0));
+    }
+
+    #[test]
+    fn test_par_array_init_cells() {
+        let counters: [std::cell::Cell<usize>; 8] = par_array_init_cells(|i| i);
+        counters[2].set(20);
+        for (i, counter) in counters.iter().enumerate() {
+            assert_eq!(counter.get(), if i == 2 { 20 } else { i });
+        }
+    }
+
+    #[test]
+    fn test_par_array_init_for_associated_len() {
+        struct Hexad;
+        impl HasArrayLen for Hexad {
+            const LEN: usize = 6;
+            type Array = [u16; Self::LEN];
+        }
+
+        let arr = par_array_init_for::<Hexad, _>(|i| i as u16 + 1);
+        assert_eq!(arr, [1, 2, 3, 4, 5, 6]);
+    }
+
+    // Sequential fills put all the work on one thread
+    #[cfg(not(feature = "deterministic"))]
+    #[test]
+    fn test_par_array_init_imbalance() {
+        use std::time::Duration;
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+        let (arr, skewed): ([usize; 64], _) = pool.install(|| {
+            par_array_init_imbalance(|i| {
+                if i == 0 {
+                    std::thread::sleep(Duration::from_millis(50));
+                }
+                i
+            })
+        });
+        assert_eq!(arr, par_array_indices());
+        assert!(skewed > 2.0, "skewed imbalance was {}", skewed);
+
+        let (_, uniform): ([usize; 64], _) = pool.install(|| {
+            par_array_init_imbalance(|i| {
+                std::thread::sleep(Duration::from_millis(2));
+                i
+            })
+        });
+        assert!(uniform < 1.5, "uniform imbalance was {}", uniform);
+    }
+
+    #[test]
+    fn test_par_array_init_stable_order_chunks_are_ascending() {
+        use std::sync::Mutex;
+
+        thread_local!(static LAST: std::cell::Cell<Option<usize>> = const { std::cell::Cell::new(None) });
+        let starts = Mutex::new(Vec::new());
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+        let arr: [usize; 1000] = pool.install(|| {
+            par_array_init_stable_order(|i| {
+                // A call that doesn't follow the previous one on this thread starts a new chunk
+                if LAST.with(|last| last.replace(Some(i))) != i.checked_sub(1) {
+                    starts.lock().unwrap().push(i);
+                }
+                i
+            })
+        });
+        assert_eq!(arr, par_array_indices());
+
+        let chunk_len = pool.install(|| default_min_len::<usize>(1000));
+        let mut starts = starts.into_inner().unwrap();
+        starts.sort_unstable();
+        assert!(starts.iter().all(|start| start % chunk_len == 0));
+    }
+
+    #[test]
+    fn test_par_array_init_range() {
+        let arr: [u32; 8] = par_array_init_range(2..5, |i| i as u32 * 10, 0);
+        assert_eq!(arr, [0, 0, 20, 30, 40, 0, 0, 0]);
+        let all_default: [String; 3] = par_array_init_range(1..1, |_| unreachable!(), "x".into());
+        assert_eq!(all_default, ["x", "x", "x"]);
+        assert!(std::panic::catch_unwind(|| {
+            par_array_init_range::<[u32; 8], _>(4..9, |i| i as u32, 0)
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_par_array_init_zip_slices() {
+        let a = [1u32, 2, 3, 4];
+        let b = vec![5u32, 6, 7, 8];
+        let products: [u32; 4] = par_array_init_zip_slices(&a, &b, |_, a, b| a * b);
+        assert_eq!(products, [5, 12, 21, 32]);
+        assert!(std::panic::catch_unwind(|| {
+            par_array_init_zip_slices::<[u32; 4], _, _, _>(&a, &b[..3], |_, a, b| a * b)
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_try_par_array_init_catch_with_nested_catch_unwind() {
+        use std::sync::Mutex;
+
+        let recovered = Mutex::new(Vec::new());
+        let arr: Result<[usize; 32], InitFailure<()>> = try_par_array_init_catch(|i| {
+            let elem = std::panic::catch_unwind(|| {
+                if i % 5 == 0 {
+                    panic!("nested");
+                }
+                i
+            })
+            .unwrap_or_else(|_| {
+                recovered.lock().unwrap().push(i);
+                0
+            });
+            Ok(elem)
+        });
+        let arr = arr.unwrap();
+        let mut recovered = recovered.into_inner().unwrap();
+        recovered.sort_unstable();
+        assert_eq!(recovered, [0, 5, 10, 15, 20, 25, 30]);
+        assert!(arr
+            .iter()
+            .enumerate()
+            .all(|(i, elem)| *elem == if i % 5 == 0 { 0 } else { i }));
+    }
+
+    #[test]
+    fn test_par_array_init_argsort() {
+        let values = [30, -4, 12, 7, -4];
+        let (arr, perm): ([i32; 5], _) = par_array_init_argsort(|i| values[i], i32::cmp);
+        assert_eq!(arr, values);
+        assert_eq!(perm, [1, 4, 3, 2, 0]);
+        assert!(perm.windows(2).all(|pair| arr[pair[0]] <= arr[pair[1]]));
+    }
+
+    #[test]
+    fn test_par_array_init_checksummed() {
+        let sum = |elems: &[u64]| elems.iter().sum::<u64>();
+        let arr: Result<[u64; 100], _> = par_array_init_checksummed(|i| i as u64, 4950, sum);
+        assert_eq!(arr.map(|arr| arr[99]), Ok(99));
+        let arr: Result<[u64; 100], _> = par_array_init_checksummed(|i| i as u64, 1, sum);
+        assert_eq!(
+            arr.unwrap_err(),
+            ChecksumError {
+                expected: 1,
+                actual: 4950
+            }
+        );
+    }
+
+    #[test]
+    fn test_par_array_init_sized() {
+        let len = [4usize, 16, 20][1];
+        let strings = par_array_init_sized!(len, |i: usize| i.to_string()).unwrap();
+        assert_eq!(strings.len(), 16);
+        assert_eq!(strings[15], "15");
+        let empty: Option<Box<[u8]>> = par_array_init_sized!(0, |_| 1);
+        assert_eq!(empty.unwrap().len(), 0);
+        assert!(par_array_init_sized!(20, |i| i).is_none());
+    }
+
+    #[test]
+    fn test_par_array_init_double_buffered() {
+        let arr: Box<[u64; 8192]> = Box::new(par_array_init_double_buffered(|i| i as u64 * 3));
+        assert!(arr
+            .iter()
+            .enumerate()
+            .all(|(i, elem)| *elem == i as u64 * 3));
+        let strings: [String; 100] = par_array_init_double_buffered(|i| i.to_string());
+        assert_eq!(strings[99], "99");
+        let empty: [u64; 0] = par_array_init_double_buffered(|i| i as u64);
+        assert_eq!(empty, []);
+    }
+
+    #[test]
+    fn test_par_array_init_double_buffered_drops_elements_on_panic() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CREATED: AtomicUsize = AtomicUsize::new(0);
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+        struct Tracked;
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let result = std::panic::catch_unwind(|| {
+            par_array_init_double_buffered::<[Tracked; 1000], _>(|i| {
+                if i == 700 {
+                    panic!("boom");
+                }
+                CREATED.fetch_add(1, Ordering::SeqCst);
+                Tracked
+            })
+        });
+        assert!(result.is_err());
+        assert_eq!(DROPS.load(Ordering::SeqCst), CREATED.load(Ordering::SeqCst));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_par_array_init_channel() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let recv = par_array_init_channel::<[usize; 64], _>(|i| i * 2);
+        let array = futures::executor::block_on(recv).unwrap();
+        assert_eq!(array, par_array_init::<[usize; 64], _>(|i| i * 2));
+        if SEQUENTIAL {
+            // The fill is finished before the receiver is returned, so there's nothing to cancel
+            return;
+        }
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = calls.clone();
+        let recv = par_array_init_channel::<[usize; 4096], _>(move |i| {
+            counted.fetch_add(1, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            i
+        });
+        drop(recv);
+        // The initializer, and its clone of calls, is dropped once the background fill is done
+        while Arc::strong_count(&calls) > 1 {
+            std::thread::yield_now();
+        }
+        assert!(calls.load(Ordering::SeqCst) < 4096);
+    }
+
+    #[test]
+    fn test_par_array_init_budgeted() {
+        let arr: Result<[Vec<u8>; 16], _> =
+            par_array_init_budgeted(16 * 10, |_| vec![0u8; 10], Vec::len);
+        assert!(arr.is_ok());
+
+        let arr: Result<[Vec<u8>; 16], _> =
+            par_array_init_budgeted(100, |_| vec![0u8; 10], Vec::len);
+        let error = arr.unwrap_err();
+        assert_eq!(error.budget, 100);
+        assert!(error.index < 16);
+    }
+
+    #[test]
+    fn test_par_array_init_symmetric() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = AtomicUsize::new(0);
+        let matrix: [[usize; 3]; 3] = par_array_init_symmetric(|i, j| {
+            assert!(i <= j);
+            calls.fetch_add(1, Ordering::Relaxed);
+            i + j * 10
+        });
+        assert_eq!(matrix, [[0, 10, 20], [10, 11, 21], [20, 21, 22]]);
+        assert_eq!(calls.load(Ordering::Relaxed), 6);
+    }
+
+    #[test]
+    fn test_par_array_init_thread_map_covers_the_array() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+        let (arr, chunks): ([usize; 1000], _) = pool.install(|| par_array_init_thread_map(|i| i));
+        assert_eq!(arr, par_array_indices());
+        assert_eq!(chunks.first().map(|(range, _)| range.start), Some(0));
+        assert_eq!(chunks.last().map(|(range, _)| range.end), Some(1000));
+        assert!(chunks
+            .windows(2)
+            .all(|pair| pair[0].0.end == pair[1].0.start));
+        assert!(chunks.iter().all(|(_, thread)| *thread < 4));
+    }
+
+    #[cfg(not(feature = "deterministic"))]
+    #[test]
+    fn test_par_array_init_scoped_partitions_evenly() {
+        use std::sync::Mutex;
+
+        let threads = Mutex::new([None; 9]);
+        let arr: [u32; 9] = par_array_init_scoped(3, |i| {
+            threads.lock().unwrap()[i] = Some(std::thread::current().id());
+            i as u32 * 2
+        });
+        assert_eq!(arr, [0, 2, 4, 6, 8, 10, 12, 14, 16]);
+        let threads = threads.into_inner().unwrap().map(Option::unwrap);
+        for run in threads.chunks(3) {
+            assert!(run.iter().all(|&id| id == run[0]));
+        }
+        assert_ne!(threads[0], threads[3]);
+        assert_ne!(threads[3], threads[6]);
+        assert_ne!(threads[0], threads[6]);
+        assert!(!threads.contains(&std::thread::current().id()));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_par_array_init_async_each_works() {
+        let array: [u32; 8] = par_array_init_async_each(|i| async move { i as u32 * 3 });
+        assert_eq!(array, [0, 3, 6, 9, 12, 15, 18, 21]);
+    }
+
+    #[test]
+    fn test_par_array_init_until_fills_sentinel_after_stop() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let array: [i32; 8] =
+            pool.install(|| par_array_init_until(|i| i as i32 * 10, |&x: &i32| x == 40, -1));
+        assert_eq!(array, [0, 10, 20, 30, 40, -1, -1, -1]);
+    }
+
+    #[test]
+    fn test_par_array_init_tiled_is_block_major() {
+        let tiles: [[u8; 4]; 3] = par_array_init_tiled(|b, o| (b * 4 + o) as u8);
+        assert_eq!(tiles, [[0, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11]]);
+        let tiles: Box<[[(usize, usize); 3]; 5000]> = Box::new(par_array_init_tiled(|b, o| (b, o)));
+        for (b, block) in tiles.iter().enumerate() {
+            assert_eq!(*block, [(b, 0), (b, 1), (b, 2)]);
+        }
+    }
+
+    #[test]
+    fn test_par_array_init_arc_ctx_looks_up_context() {
+        let table = std::sync::Arc::new((0..16).map(|i| i * i).collect::<Vec<u32>>());
+        let array: [u32; 16] =
+            par_array_init_arc_ctx(table.clone(), |table: &Vec<u32>, i| table[15 - i]);
+        let expected: [u32; 16] = std::array::from_fn(|i| ((15 - i) * (15 - i)) as u32);
+        assert_eq!(array, expected);
+        assert_eq!(std::sync::Arc::strong_count(&table), 1);
+    }
+
+    #[test]
+    fn test_from_par_iter_strict_returns_mismatched_elements() {
+        let array: Result<[i32; 4], Vec<i32>> = from_par_iter_strict(vec![1, 2, 3, 4]);
+        assert_eq!(array, Ok([1, 2, 3, 4]));
+        let array: Result<[i32; 4], Vec<i32>> = from_par_iter_strict(vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(array, Err(vec![1, 2, 3, 4, 5, 6]));
+        let array: Result<[i32; 4], Vec<i32>> = from_par_iter_strict(vec![1, 2]);
+        assert_eq!(array, Err(vec![1, 2]));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_fill_reports_metrics() {
+        use metrics::{Counter, Gauge, Histogram, Key, KeyName, Metadata, SharedString, Unit};
+        use std::collections::HashMap;
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Default)]
+        struct Counters(Mutex<HashMap<String, Arc<AtomicU64>>>);
+
+        impl metrics::Recorder for Counters {
+            fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+            fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+            fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+            fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+                let mut counters = self.0.lock().unwrap();
+                Counter::from_arc(counters.entry(key.name().to_owned()).or_default().clone())
+            }
+
+            fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+                Gauge::noop()
+            }
+
+            fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+                Histogram::noop()
+            }
+        }
+
+        // The calls and elements counted while running `fill`
+        fn count(fill: impl FnOnce()) -> (u64, u64) {
+            let recorder = Counters::default();
+            metrics::with_local_recorder(&recorder, fill);
+            let counters = recorder.0.lock().unwrap();
+            let load = |name: &str| counters[name].load(Ordering::Relaxed);
+            (
+                load("par_array_init.calls"),
+                load("par_array_init.elements"),
+            )
+        }
+
+        assert_eq!(
+            count(|| assert_eq!(par_array_init::<[usize; 40], _>(|i| i)[39], 39)),
+            (1, 40)
+        );
+        assert_eq!(
+            count(|| assert_eq!(from_par_iter(vec![7]), Some([7]))),
+            (1, 1)
+        );
+        // Built from a fill of the upper triangle and a fill of the rows, but still one fill
+        assert_eq!(
+            count(|| {
+                let array: [[usize; 3]; 3] = par_array_init_symmetric(|i, j| i * j);
+                assert_eq!(array[2], [0, 2, 4]);
+            }),
+            (1, 3)
+        );
+    }
+
+    #[test]
+    fn test_par_boxed_array_init_never_builds_on_the_stack() {
+        // 800KB of elements can't fit in the thread's 64KB stack, so building the array there would overflow
+        let array = std::thread::Builder::new()
+            .stack_size(64 * 1024)
+            .spawn(|| par_array_init::<Box<[u64; 100_000]>, _>(|i| i as u64))
+            .unwrap()
+            .join()
+            .unwrap();
+        assert!(array.iter().enumerate().all(|(i, &x)| x == i as u64));
+    }
+
+    #[test]
+    fn test_par_array_init_builds_array_of_results() {
+        let array = par_array_init::<[Result<u32, String>; 8], _>(|i| {
+            if i == 3 {
+                Err("bad".into())
+            } else {
+                Ok(i as u32)
+            }
+        });
+        for (i, slot) in array.iter().enumerate() {
+            match slot {
+                Ok(x) => assert_eq!(*x, i as u32),
+                Err(e) => assert_eq!((i, e.as_str()), (3, "bad")),
+            }
+        }
+        assert_eq!(transpose_results(array), Err("bad".to_owned()));
+        let all_ok: [Result<u32, String>; 4] = par_array_init(|i| Ok(i as u32));
+        assert_eq!(transpose_results(all_ok), Ok([0, 1, 2, 3]));
+    }
+
+    #[test]
+    fn test_from_par_iter_or_panic_works() {
+        let array: [i32; 4] = from_par_iter_or_panic(vec![1, 2, 3, 4]);
+        assert_eq!(array, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "iterator has a different length than the array\n  left: 6\n right: 4"
+    )]
+    fn test_from_par_iter_or_panic_panics_on_mismatch() {
+        let _: [i32; 4] = from_par_iter_or_panic(vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn test_par_array_init_to_file_writes_every_element() {
+        use std::io::{Read, Seek, SeekFrom};
+
+        let path =
+            std::env::temp_dir().join(format!("par_array_init_to_file_{}", std::process::id()));
+        par_array_init_to_file(&path, 10_000, |i| i as u32 * 7).unwrap();
+        let mut file = std::fs::File::open(&path).unwrap();
+        assert_eq!(file.metadata().unwrap().len(), 10_000 * 4);
+        for i in [0, 1, 4_321, 9_999] {
+            let mut bytes = [0; 4];
+            file.seek(SeekFrom::Start(i * 4)).unwrap();
+            file.read_exact(&mut bytes).unwrap();
+            assert_eq!(u32::from_ne_bytes(bytes), i as u32 * 7);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_par_array_init_from_array_doubles_source() {
+        let src: [u32; 8] = par_array_init(|i| i as u32);
+        let doubled: [u32; 8] = par_array_init_from_array(&src, |src: &[u32; 8], i| src[i] * 2);
+        assert_eq!(doubled, [0, 2, 4, 6, 8, 10, 12, 14]);
+    }
+
+    #[test]
+    fn test_try_par_array_init_reports_lowest_error_under_any_schedule() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+        for run in 0..200 {
+            // 64 byte elements put indices 2 and 5 in separate tasks, and the delay lets either fail first
+            let array: Result<[[u64; 8]; 64], usize> = pool.install(|| {
+                try_par_array_init(|i| {
+                    if i == 2 || i == 5 {
+                        if (i == 2) == (run % 2 == 0) {
+                            std::thread::sleep(std::time::Duration::from_micros(200));
+                        }
+                        Err(i)
+                    } else {
+                        Ok([i as u64; 8])
+                    }
+                })
+            });
+            assert_eq!(array, Err(2));
+        }
+    }
+
+    #[cfg(not(feature = "deterministic"))]
+    #[test]
+    fn test_par_array_init_pinned_gives_each_thread_one_run() {
+        use std::sync::Mutex;
+
+        let threads = Mutex::new([None; 64]);
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+        let array: [u32; 64] = pool.install(|| {
+            par_array_init_pinned(|i| {
+                threads.lock().unwrap()[i] = rayon::current_thread_index();
+                i as u32
+            })
+        });
+        assert!(array.iter().enumerate().all(|(i, &x)| x == i as u32));
+        let threads = threads.into_inner().unwrap();
+        for (t, run) in threads.chunks(16).enumerate() {
+            assert!(run.iter().all(|&thread| thread == Some(t)));
+        }
+    }
+
+    #[test]
+    fn test_par_array_init_pooled_reuses_buffers() {
+        let pool = ObjectPool::new(|| Vec::with_capacity(1024));
+        let pool_threads = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        let array: [Vec<u8>; 16] = pool_threads.install(|| {
+            par_array_init_pooled(&pool, |mut buf: PooledRef<'_, Vec<u8>>, i| {
+                buf.clear();
+                buf.extend((0..4).map(|j| (i * 4 + j) as u8));
+                buf.to_vec()
+            })
+        });
+        assert_eq!(array[3], [12, 13, 14, 15]);
+        // At most one buffer is in use per thread at a time
+        assert!(pool.created() <= 2);
+        let detached = pool.get().detach();
+        assert_eq!(detached.capacity(), 1024);
+    }
+
+    #[test]
+    fn test_par_array_init_monotonic_finds_first_decrease() {
+        let array: Result<[usize; 16], _> = par_array_init_monotonic(|i| i);
+        assert_eq!(array, Ok(std::array::from_fn(|i| i)));
+        let array: Result<[usize; 16], _> = par_array_init_monotonic(|i| 16 - i);
+        assert_eq!(array, Err(MonotonicityError { index: 0 }));
+        let array: Result<[f64; 4], _> =
+            par_array_init_monotonic(|i| if i == 2 { f64::NAN } else { i as f64 });
+        assert_eq!(array, Err(MonotonicityError { index: 1 }));
+    }
+
+    #[test]
+    fn test_from_btreemap_needs_every_index() {
+        use std::collections::BTreeMap;
+
+        let map: BTreeMap<usize, String> = (0..4).rev().map(|i| (i, i.to_string())).collect();
+        let array: Option<[String; 4]> = from_btreemap(map);
+        assert_eq!(array, Some(["0", "1", "2", "3"].map(String::from)));
+        let gap: BTreeMap<usize, String> = [0, 1, 3, 4].map(|i| (i, i.to_string())).into();
+        assert_eq!(from_btreemap::<[String; 4]>(gap), None);
+        let short: BTreeMap<usize, String> = [(0, "0".to_owned())].into();
+        assert_eq!(from_btreemap::<[String; 4]>(short), None);
+        assert_eq!(from_btreemap::<[String; 0]>(BTreeMap::new()), Some([]));
+    }
+
+    #[cfg(all(feature = "perf", target_os = "linux"))]
+    #[test]
+    fn test_par_array_init_perf_counts_fill() {
+        let work = |i: usize| (0..64).fold(i as u64, |acc, x| acc.wrapping_mul(31) ^ x);
+        let (array, counts): ([u64; 256], _) = par_array_init_perf(work);
+        assert!(array.iter().enumerate().all(|(i, &x)| x == work(i)));
+        // Machines without a PMU can't count anything, but one that can sees the fill's instructions
+        if let Some(instructions) = counts.instructions {
+            assert!(instructions > 0);
+        }
+    }
+
+    #[cfg(feature = "bitvec")]
+    #[test]
+    fn test_par_array_update_dirty_only_recomputes_dirty() {
+        let mut array: [String; 8] = par_array_init(|i| i.to_string());
+        let mut dirty: bitvec::BitArr!(for 8) = bitvec::array::BitArray::ZERO;
+        dirty.set(1, true);
+        dirty.set(4, true);
+        par_array_update_dirty(&mut array, &dirty, |i| format!("new {}", i));
+        assert_eq!(
+            array,
+            ["0", "new 1", "2", "3", "new 4", "5", "6", "7"].map(String::from)
+        );
+    }
+
+    #[test]
+    fn test_par_array_init_grouped_splits_by_key() {
+        let grouped = par_array_init_grouped::<[u32; 8], _, _>(|i| i as u32 * 10, |i| i % 2, 2);
+        assert_eq!(grouped, [vec![0, 20, 40, 60], vec![10, 30, 50, 70]]);
+        let grouped = par_array_init_grouped::<[u32; 4], _, _>(|i| i as u32, |_| 0, 3);
+        assert_eq!(grouped, [vec![0, 1, 2, 3], vec![], vec![]]);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_counter_rng_matches_philox_known_answers() {
+        // Known answer vectors from the Random123 distribution
+        assert_eq!(
+            CounterRng::philox([0; 4], [0; 2]),
+            [0x6627_e8d5, 0xe169_c58d, 0xbc57_ac4c, 0x9b00_dbd8]
+        );
+        assert_eq!(
+            CounterRng::philox([u32::MAX; 4], [u32::MAX; 2]),
+            [0x408f_276d, 0x41c8_3b0e, 0xa20b_c7c6, 0x6d54_51fd]
+        );
+        assert_eq!(
+            CounterRng::philox(
+                [0x243f_6a88, 0x85a3_08d3, 0x1319_8a2e, 0x0370_7344],
+                [0xa409_3822, 0x299f_31d0]
+            ),
+            [0xd16c_fe09, 0x94fd_cceb, 0x5001_e420, 0x2412_6ea1]
+        );
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_par_array_init_cbrng_ignores_thread_count() {
+        use rand::RngCore;
+
+        let fill = |threads| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            pool.install(|| {
+                par_array_init_cbrng::<[u64; 64], _>(7, |rng: &mut CounterRng, i| {
+                    // Draw a different amount per index so streams have to stay independent
+                    (0..i % 5).fold(rng.next_u64(), |acc, _| acc ^ rng.next_u64())
+                })
+            })
+        };
+        let single = fill(1);
+        assert_eq!(fill(2), single);
+        assert_eq!(fill(4), single);
+        assert_ne!(single[0], single[1]);
+    }
+
+    #[test]
+    fn test_par_array_init_lazy_defers_calls() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = AtomicUsize::new(0);
+        let cells = par_array_init_lazy::<8, _, _>(|i| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            i * 3
+        });
+        assert_eq!(calls.load(Ordering::Relaxed), 0);
+        assert_eq!(*cells[5], 15);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert_eq!(*cells[5], 15);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        let forced: Vec<usize> = cells.iter().map(|cell| **cell).collect();
+        assert_eq!(forced, [0, 3, 6, 9, 12, 15, 18, 21]);
+        assert_eq!(calls.load(Ordering::Relaxed), 8);
+    }
+
+    #[test]
+    fn test_par_array_init_histogram_counts_buckets() {
+        let (array, histogram): ([usize; 7], _) =
+            par_array_init_histogram(|i| i, 4, |&elem: &usize| elem / 2);
+        assert_eq!(array, [0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(histogram, [2, 2, 2, 1]);
+    }
+
+    #[test]
+    fn test_try_par_array_init_with_cleanup_releases_completed_elements() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let released: [AtomicUsize; 8] = std::array::from_fn(|_| AtomicUsize::new(0));
+        let release = |handle: &mut usize| {
+            released[*handle].fetch_add(1, Ordering::Relaxed);
+        };
+        let array: Result<[usize; 8], &str> = pool.install(|| {
+            try_par_array_init_with_cleanup(
+                |i| if i == 3 { Err("cancelled") } else { Ok(i) },
+                release,
+            )
+        });
+        assert_eq!(array, Err("cancelled"));
+        let counts = released
+            .each_ref()
+            .map(|count| count.swap(0, Ordering::Relaxed));
+        assert_eq!(counts, [1, 1, 1, 0, 0, 0, 0, 0]);
+
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            pool.install(|| {
+                try_par_array_init_with_cleanup::<[usize; 8], _, _, ()>(
+                    |i| {
+                        if i == 3 {
+                            panic!("lost the device")
+                        } else {
+                            Ok(i)
+                        }
+                    },
+                    release,
+                )
+            })
+        }));
+        assert!(panicked.is_err());
+        let counts = released
+            .each_ref()
+            .map(|count| count.load(Ordering::Relaxed));
+        assert_eq!(counts, [1, 1, 1, 0, 0, 0, 0, 0]);
+
+        let array: Result<[usize; 8], ()> = try_par_array_init_with_cleanup(Ok, release);
+        assert_eq!(array, Ok([0, 1, 2, 3, 4, 5, 6, 7]));
+    }
+
+    #[test]
+    fn test_par_array_init_soa_splits_fields() {
+        let ids = vec![1u32, 2, 3, 4];
+        let weights = vec![0.5f32, 1.5, 2.5, 3.5];
+        let soa: Option<([u32; 4], [f32; 4])> = par_array_init_soa(ids, weights);
+        assert_eq!(soa, Some(([1, 2, 3, 4], [0.5, 1.5, 2.5, 3.5])));
+        let short: Option<([u32; 4], [String; 4])> =
+            par_array_init_soa(vec![1, 2, 3, 4], vec!["a".to_owned()]);
+        assert_eq!(short, None);
+    }
+
+    #[test]
+    fn test_par_array_init_yielding_works() {
+        let array: [u64; 32] = par_array_init_yielding(|i, hook: YieldNow| {
+            (0..4).fold(i as u64, |acc, step| {
+                hook.yield_now();
+                acc + step
+            })
+        });
+        let expected: [u64; 32] = std::array::from_fn(|i| i as u64 + 6);
+        assert_eq!(array, expected);
+    }
+
+    #[test]
+    fn test_par_array_newtype_init_deref_and_map() {
+        let array = ParArray::<usize, 6>::init(|i| i * 2);
+        assert_eq!(array.0, [0, 2, 4, 6, 8, 10]);
+        // Deref lets it be used like the inner array
+        assert_eq!(array.len(), 6);
+        assert_eq!(array[3], 6);
+        assert_eq!(array.iter().sum::<usize>(), 30);
+        let mut strings = array.par_map(|x| x.to_string());
+        strings[0] = "zero".to_owned();
+        assert_eq!(strings.into_inner(), ["zero", "2", "4", "6", "8", "10"]);
+        let sums = array.par_zip_map(ParArray([1; 6]), |a, b| a + b);
+        assert_eq!(sums, ParArray([1, 3, 5, 7, 9, 11]));
+        let filled: ParArray<u8, 4> = par_array_init(|i| i as u8);
+        assert_eq!(<[u8; 4]>::from(filled), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_par_all_and_par_any() {
+        let array: [u32; 8] = par_array_init(|i| i as u32);
+        assert!(par_all(&array, |x: &u32| *x < 100));
+        assert!(!par_all(&array, |x: &u32| *x < 7));
+        assert!(par_any(&array, |x: &u32| *x == 3));
+        assert!(!par_any(&array, |x: &u32| *x == 30));
+        assert!(par_all(&ParArray(array), |x: &u32| *x < 8));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_par_array_init_join_all_keeps_positions() {
+        let array: [usize; 8] =
+            futures::executor::block_on(par_array_init_join_all(|i| async move {
+                // Later indices finish first, but the outputs still land in index order
+                for _ in 0..8 - i {
+                    let mut yielded = false;
+                    futures::future::poll_fn(|cx| {
+                        if std::mem::replace(&mut yielded, true) {
+                            std::task::Poll::Ready(())
+                        } else {
+                            cx.waker().wake_by_ref();
+                            std::task::Poll::Pending
+                        }
+                    })
+                    .await;
+                }
+                i * 2
+            }));
+        assert_eq!(array, [0, 2, 4, 6, 8, 10, 12, 14]);
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn test_from_inner_array_round_trips_transparent_wrapper() {
+        #[repr(transparent)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        struct Id(u64);
+        // SAFETY: `Id` is `#[repr(transparent)]` over `u64`
+        unsafe impl bytemuck::TransparentWrapper<u64> for Id {}
+
+        let ids: [Id; 8] = from_inner_array(par_array_init(|i| i as u64 * 3));
+        let direct: [Id; 8] = par_array_init(|i| Id(i as u64 * 3));
+        assert_eq!(ids, direct);
+        let inner: [u64; 8] = into_inner_array(ids);
+        assert_eq!(inner, [0, 3, 6, 9, 12, 15, 18, 21]);
+    }
+
+    #[cfg(all(feature = "shmem", target_os = "linux"))]
+    #[test]
+    fn test_par_fill_shmem_visible_through_second_mapping() {
+        const LEN: usize = 4096;
+        // SAFETY: both mappings are of the same `LEN` byte memfd, alive until the munmaps at the end
+        unsafe {
+            let fd = libc::memfd_create(b"par_fill_shmem\0".as_ptr().cast(), 0);
+            assert!(fd >= 0);
+            assert_eq!(libc::ftruncate(fd, LEN as libc::off_t), 0);
+            let map = || {
+                let addr = libc::mmap(
+                    std::ptr::null_mut(),
+                    LEN,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED,
+                    fd,
+                    0,
+                );
+                assert_ne!(addr, libc::MAP_FAILED);
+                addr.cast::<u8>()
+            };
+            let (writer, reader) = (map(), map());
+            assert_ne!(writer, reader);
+
+            let region = std::slice::from_raw_parts_mut(writer, LEN);
+            assert_eq!(par_fill_shmem(region, |i| i as u32 * 7), Ok(()));
+            assert_eq!(
+                par_fill_shmem::<u32, _>(&mut region[1..5], |i| i as u32),
+                Err(ShmemError::Misaligned { align: 4 })
+            );
+
+            let words: &[u32] = bytemuck::cast_slice(std::slice::from_raw_parts(reader, LEN));
+            assert!(words.iter().enumerate().all(|(i, &w)| w == i as u32 * 7));
+
+            assert_eq!(libc::munmap(writer.cast(), LEN), 0);
+            assert_eq!(libc::munmap(reader.cast(), LEN), 0);
+            assert_eq!(libc::close(fd), 0);
+        }
+    }
+
+    #[test]
+    fn test_par_array_init_dag_computes_dependencies_first() {
+        let array: [u32; 4] = par_array_init_dag(
+            |i| match i {
+                1 | 2 => vec![0],
+                3 => vec![1, 2],
+                _ => vec![],
+            },
+            |slots: &[Option<u32>], i| match i {
+                0 => 1,
+                1 => slots[0].unwrap() + 10,
+                2 => slots[0].unwrap() * 5,
+                _ => slots[1].unwrap() + slots[2].unwrap(),
+            },
+        )
+        .unwrap();
+        assert_eq!(array, [1, 11, 5, 16]);
+    }
+
+    #[test]
+    fn test_par_array_init_dag_errors_on_cycle() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = AtomicUsize::new(0);
+        let result: Result<[u32; 5], CycleError> = par_array_init_dag(
+            |i| match i {
+                1 => vec![3],
+                2 => vec![1],
+                3 => vec![2],
+                4 => vec![1],
+                _ => vec![],
+            },
+            |_: &[Option<u32>], i| {
+                calls.fetch_add(1, Ordering::Relaxed);
+                i as u32
+            },
+        );
+        let index = result.unwrap_err().index;
+        assert!((1..=3).contains(&index));
+        assert_eq!(calls.load(Ordering::Relaxed), 0);
+
+        let result: Result<[u32; 2], CycleError> =
+            par_array_init_dag(|i| vec![i], |_: &[Option<u32>], i| i as u32);
+        assert_eq!(result, Err(CycleError { index: 0 }));
+    }
+
+    #[test]
+    fn test_par_array_init_manually_drop_reclaims() {
+        let mut array: std::mem::ManuallyDrop<[u32; 8]> =
+            par_array_init_manually_drop(|i| i as u32 + 1);
+        assert_eq!(*array, [1, 2, 3, 4, 5, 6, 7, 8]);
+        let reclaimed: [u32; 8] = unsafe { reclaim_manually_drop(&mut array) };
+        assert_eq!(reclaimed, [1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[cfg(not(feature = "deterministic"))]
+    #[test]
+    fn test_par_array_init_recurrence_window_zero_is_parallel() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+        let threads = std::sync::Mutex::new(std::collections::HashSet::new());
+        let array: [usize; 4096] = pool.install(|| {
+            par_array_init_recurrence(0, |previous: &[usize], i| {
+                assert!(previous.is_empty());
+                threads.lock().unwrap().insert(std::thread::current().id());
+                // Long enough per element that the other workers steal some of the fill
+                std::thread::sleep(std::time::Duration::from_micros(50));
+                i * 3
+            })
+        });
+        assert!(array.iter().enumerate().all(|(i, &elem)| elem == i * 3));
+        assert!(threads.into_inner().unwrap().len() > 1);
+    }
+}
@@ -0,0 +1,46 @@
+//! Counts allocations made by boxed fills with a counting global allocator.
+//!
+//! This is its own test binary so the allocator only sees this file's single test.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static LARGE_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+const LEN: usize = 1 << 16;
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        if layout.size() >= LEN * std::mem::size_of::<u64>() {
+            LARGE_ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+#[test]
+fn par_boxed_array_init_allocates_once() {
+    // Start the global pool up front so its own allocations aren't counted, waiting for every worker since a sequential fill won't
+    let _: Box<[u64; 16]> = par_array_init::par_boxed_array_init(|i| i as u64);
+    rayon::broadcast(|_| ());
+
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    let large_before = LARGE_ALLOCATIONS.load(Ordering::SeqCst);
+    let array: Box<[u64; LEN]> = par_array_init::par_boxed_array_init(|i| i as u64);
+    let allocations = ALLOCATIONS.load(Ordering::SeqCst) - before;
+    let large = LARGE_ALLOCATIONS.load(Ordering::SeqCst) - large_before;
+
+    assert_eq!(array[LEN - 1], LEN as u64 - 1);
+    assert_eq!(large, 1);
+    assert_eq!(allocations, 1);
+}
@@ -0,0 +1,75 @@
+//! The concurrency limit is process wide, so it's tested in its own binary where it can't slow down or deadlock the other tests.
+
+use par_array_init::{
+    par_array_init, par_array_init_grouped, par_array_init_in_scope,
+    par_array_init_with_chunk_hook, par_array_init_with_reduce, set_global_concurrency_limit,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+/// Held by every test, they share the limit and the counters below.
+static SERIAL: Mutex<()> = Mutex::new(());
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+static MAX_RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// Initializer that keeps track of how many calls to it are running at once.
+fn counted(i: usize) -> usize {
+    let running = RUNNING.fetch_add(1, Ordering::SeqCst) + 1;
+    MAX_RUNNING.fetch_max(running, Ordering::SeqCst);
+    std::thread::sleep(Duration::from_micros(200));
+    RUNNING.fetch_sub(1, Ordering::SeqCst);
+    i * 2
+}
+
+/// Run `fill` from 4 threads at once on an 8 thread pool under a limit of 2, returning its results and the most calls to `counted` that were ever running together.
+fn under_limit<T: Send>(fill: impl Fn() -> T + Sync) -> (Vec<T>, usize) {
+    let _serial = SERIAL.lock().unwrap_or_else(PoisonError::into_inner);
+    MAX_RUNNING.store(0, Ordering::SeqCst);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(8)
+        .build()
+        .unwrap();
+    set_global_concurrency_limit(2);
+    let results = std::thread::scope(|scope| {
+        let fills: Vec<_> = (0..4)
+            .map(|_| scope.spawn(|| pool.install(&fill)))
+            .collect();
+        fills.into_iter().map(|fill| fill.join().unwrap()).collect()
+    });
+    set_global_concurrency_limit(0);
+    (results, MAX_RUNNING.load(Ordering::SeqCst))
+}
+
+#[test]
+fn concurrent_fills_respect_the_limit() {
+    let (arrays, max_running) = under_limit(|| par_array_init::<[usize; 64], _>(counted));
+    for array in &arrays {
+        assert!(array.iter().enumerate().all(|(i, elem)| *elem == i * 2));
+    }
+    assert!(max_running <= 2);
+}
+
+#[test]
+fn hand_rolled_fills_respect_the_limit() {
+    let (arrays, max_running) = under_limit(|| par_array_init_in_scope::<[usize; 64], _>(counted));
+    assert!(arrays.iter().all(|array| array[63] == 126));
+    assert!(max_running <= 2);
+
+    let (arrays, max_running) =
+        under_limit(|| par_array_init_with_chunk_hook::<[usize; 64], _, _>(counted, |_| {}));
+    assert!(arrays.iter().all(|array| array[63] == 126));
+    assert!(max_running <= 2);
+
+    let (sums, max_running) = under_limit(|| {
+        let (_, sum) = par_array_init_with_reduce::<[usize; 64], _, _>(counted, 0, |a, b| a + b);
+        sum
+    });
+    assert!(sums.iter().all(|&sum| sum == 63 * 64));
+    assert!(max_running <= 2);
+
+    let (groups, max_running) =
+        under_limit(|| par_array_init_grouped::<[usize; 64], _, _>(counted, |i| i % 2, 2));
+    assert!(groups.iter().all(|groups| groups[1][0] == 2));
+    assert!(max_running <= 2);
+}
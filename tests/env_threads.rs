@@ -0,0 +1,16 @@
+//! Setting an environment variable races with any other thread reading the environment, so the env var fill is tested in its own binary where no other tests are running.
+
+use par_array_init::{par_array_init_env, THREADS_ENV_VAR};
+
+#[test]
+fn par_array_init_env_honors_thread_count() {
+    std::env::set_var(THREADS_ENV_VAR, "3");
+    let array: [usize; 16] = par_array_init_env(|_| rayon::current_num_threads());
+    assert!(array.iter().all(|&threads| threads == 3));
+
+    std::env::remove_var(THREADS_ENV_VAR);
+    let array: [usize; 16] = par_array_init_env(|_| rayon::current_num_threads());
+    assert!(array
+        .iter()
+        .all(|&threads| threads == rayon::current_num_threads()));
+}
@@ -0,0 +1,215 @@
+//! Drop accounting for the `MaybeUninit` based fills, meant to be run under Miri so any read of uninitialized memory is caught too.
+//!
+//! These run as ordinary tests as well, but Miri is what checks for undefined behavior:
+//!
+//! ```text
+//! MIRIFLAGS="-Zmiri-tree-borrows -Zmiri-permissive-provenance -Zmiri-ignore-leaks" \
+//!     cargo +nightly miri test --test miri
+//! ```
+//!
+//! The flags are for rayon's thread pool rather than this crate: crossbeam-epoch trips Stacked Borrows and casts integers to pointers, and pool threads outlive the tests with their allocations. Leaks of our own elements are caught by the registry instead.
+//!
+//! Every element records itself in a registry when it's created and removes itself when it's dropped, so a leak leaves an entry behind and a double drop finds its entry already gone.
+use par_array_init::{
+    from_par_iter, par_array_init, par_array_init_manually_drop, par_array_init_soa,
+    reclaim_manually_drop, try_par_array_init,
+};
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+// Small enough to keep Miri's runtime reasonable while still splitting over a few tasks
+const LEN: usize = 32;
+
+#[derive(Default)]
+struct Registry {
+    next_id: AtomicUsize,
+    alive: Mutex<HashSet<usize>>,
+}
+
+impl Registry {
+    fn track(&self) -> Tracked<'_> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        self.alive.lock().unwrap().insert(id);
+        Tracked { id, registry: self }
+    }
+
+    fn assert_all_dropped(&self) {
+        assert!(
+            self.alive.lock().unwrap().is_empty(),
+            "elements were leaked"
+        );
+    }
+}
+
+struct Tracked<'a> {
+    id: usize,
+    registry: &'a Registry,
+}
+
+impl Drop for Tracked<'_> {
+    fn drop(&mut self) {
+        let removed = self.registry.alive.lock().unwrap().remove(&self.id);
+        // Aborting rather than panicking, since a panic in a drop during unwinding would abort anyway without the message
+        if !removed {
+            eprintln!("element {} was dropped twice", self.id);
+            std::process::abort();
+        }
+    }
+}
+
+fn pool() -> rayon::ThreadPool {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(4)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn par_array_init_drops_every_element_once() {
+    let registry = Registry::default();
+    let array: [Tracked; LEN] = pool().install(|| par_array_init(|_| registry.track()));
+    assert_eq!(registry.alive.lock().unwrap().len(), LEN);
+    drop(array);
+    registry.assert_all_dropped();
+}
+
+#[test]
+fn par_array_init_panic_drops_written_elements() {
+    let registry = Registry::default();
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        pool().install(|| {
+            par_array_init::<[Tracked; LEN], _>(|i| {
+                if i == LEN / 2 {
+                    panic!("boom");
+                }
+                registry.track()
+            })
+        })
+    }));
+    assert!(result.is_err());
+    registry.assert_all_dropped();
+}
+
+#[test]
+fn boxed_array_drops_every_element_once() {
+    let registry = Registry::default();
+    let boxed: Box<[Tracked; LEN]> = pool().install(|| par_array_init(|_| registry.track()));
+    drop(boxed);
+    registry.assert_all_dropped();
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        pool().install(|| {
+            par_array_init::<Box<[Tracked; LEN]>, _>(|i| {
+                if i == 3 {
+                    panic!("boom");
+                }
+                registry.track()
+            })
+        })
+    }));
+    assert!(result.is_err());
+    registry.assert_all_dropped();
+}
+
+#[test]
+fn from_par_iter_handles_long_and_short_iterators() {
+    let registry = Registry::default();
+    let elems: Vec<Tracked> = (0..LEN + 4).map(|_| registry.track()).collect();
+    let array: Option<[Tracked; LEN]> = pool().install(|| from_par_iter(elems));
+    assert!(array.is_some());
+    drop(array);
+    registry.assert_all_dropped();
+
+    let elems: Vec<Tracked> = (0..LEN - 1).map(|_| registry.track()).collect();
+    let array: Option<[Tracked; LEN]> = pool().install(|| from_par_iter(elems));
+    assert!(array.is_none());
+    registry.assert_all_dropped();
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        pool().install(|| {
+            from_par_iter::<[Tracked; LEN], _>((0..LEN).into_par_iter().map(|i| {
+                if i == 5 {
+                    panic!("boom");
+                }
+                registry.track()
+            }))
+        })
+    }));
+    assert!(result.is_err());
+    registry.assert_all_dropped();
+}
+
+#[test]
+fn try_par_array_init_drops_completed_elements_on_error() {
+    let registry = Registry::default();
+    let array: Result<[Tracked; LEN], ()> =
+        pool().install(|| try_par_array_init(|_| Ok(registry.track())));
+    assert!(array.is_ok());
+    drop(array);
+    registry.assert_all_dropped();
+
+    let array: Result<[Tracked; LEN], usize> = pool()
+        .install(|| try_par_array_init(|i| if i == 7 { Err(i) } else { Ok(registry.track()) }));
+    assert_eq!(array.err(), Some(7));
+    registry.assert_all_dropped();
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        pool().install(|| {
+            try_par_array_init::<[Tracked; LEN], _, ()>(|i| {
+                if i == 9 {
+                    panic!("boom");
+                }
+                Ok(registry.track())
+            })
+        })
+    }));
+    assert!(result.is_err());
+    registry.assert_all_dropped();
+}
+
+#[test]
+fn boxed_elements_are_moved_and_dropped_once() {
+    let boxes: [Box<u32>; 8] = pool().install(|| par_array_init(|i| Box::new(i as u32)));
+    assert!(boxes.iter().enumerate().all(|(i, elem)| **elem == i as u32));
+
+    let registry = Registry::default();
+    let boxes: [Box<Tracked>; LEN] =
+        pool().install(|| par_array_init(|_| Box::new(registry.track())));
+    drop(boxes);
+    registry.assert_all_dropped();
+}
+
+#[test]
+fn soa_panic_drops_both_arrays_written_elements() {
+    let registry = Registry::default();
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        pool().install(|| {
+            let ids = (0..LEN).into_par_iter().map(|_| registry.track());
+            let names = (0..LEN).into_par_iter().map(|i| {
+                if i == LEN / 2 {
+                    panic!("boom");
+                }
+                registry.track()
+            });
+            par_array_init_soa::<LEN, _, _, _, _>(ids, names)
+        })
+    }));
+    assert!(result.is_err());
+    registry.assert_all_dropped();
+}
+
+#[test]
+fn manually_drop_is_dropped_once_reclaimed() {
+    let registry = Registry::default();
+    let mut array: std::mem::ManuallyDrop<[Tracked; LEN]> =
+        pool().install(|| par_array_init_manually_drop(|_| registry.track()));
+    let ids: Vec<usize> = array.iter().map(|elem| elem.id).collect();
+    assert_eq!(ids.len(), LEN);
+    // Stands in for the pointer an FFI caller would hand back
+    let ptr: *mut std::mem::ManuallyDrop<[Tracked; LEN]> = &mut array;
+    drop(unsafe { reclaim_manually_drop(ptr) });
+    registry.assert_all_dropped();
+}
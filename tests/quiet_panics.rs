@@ -0,0 +1,77 @@
+//! Panic hooks are process wide, so quiet fills are tested in their own binary where other tests' panics can't reach the hook.
+//!
+//! The tests here still run in parallel with each other, so each one holds `SERIAL` while it has its own hook installed.
+
+use par_array_init::{try_par_array_init_catch_quiet, InitFailure};
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+static SERIAL: Mutex<()> = Mutex::new(());
+
+#[test]
+fn caught_panics_are_not_reported() {
+    static REPORTED: AtomicUsize = AtomicUsize::new(0);
+    let _serial = SERIAL.lock().unwrap_or_else(|poison| poison.into_inner());
+    std::panic::set_hook(Box::new(|_| {
+        REPORTED.fetch_add(1, Ordering::SeqCst);
+    }));
+
+    let result = try_par_array_init_catch_quiet::<[usize; 64], _, ()>(|i| {
+        if i % 8 == 3 {
+            panic!("boom");
+        }
+        Ok(i)
+    });
+    assert!(matches!(result, Err(InitFailure::Panic(_))));
+    assert_eq!(REPORTED.load(Ordering::SeqCst), 0);
+
+    let result = try_par_array_init_catch_quiet::<[usize; 64], _, ()>(Ok);
+    assert!(matches!(result, Ok(array) if array[63] == 63));
+
+    // The counting hook is back once the fill is done
+    assert!(std::panic::catch_unwind(|| panic!("after")).is_err());
+    assert_eq!(REPORTED.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn quiet_fills_from_a_parallel_iterator_share_the_hook() {
+    static REPORTED: AtomicUsize = AtomicUsize::new(0);
+    let _serial = SERIAL.lock().unwrap_or_else(|poison| poison.into_inner());
+    std::panic::set_hook(Box::new(|_| {
+        REPORTED.fetch_add(1, Ordering::SeqCst);
+    }));
+
+    let results: Vec<_> = (0..8)
+        .into_par_iter()
+        .map(|n| {
+            try_par_array_init_catch_quiet::<[usize; 64], _, ()>(move |i| {
+                if i == 0 {
+                    // Nested on the same worker thread as the outer quiet fill
+                    let inner = try_par_array_init_catch_quiet::<[usize; 4], _, ()>(|j| {
+                        if j == 2 {
+                            panic!("inner boom");
+                        }
+                        Ok(j)
+                    });
+                    assert!(matches!(inner, Err(InitFailure::Panic(_))));
+                }
+                if n % 2 == 0 && i == 5 {
+                    panic!("boom");
+                }
+                Ok(i * n)
+            })
+        })
+        .collect();
+    for (n, result) in results.into_iter().enumerate() {
+        match result {
+            Ok(array) => assert!(n % 2 == 1 && array[63] == 63 * n),
+            Err(failure) => assert!(n % 2 == 0 && matches!(failure, InitFailure::Panic(_))),
+        }
+    }
+    assert_eq!(REPORTED.load(Ordering::SeqCst), 0);
+
+    // The counting hook is back once every fill is done
+    assert!(std::panic::catch_unwind(|| panic!("after")).is_err());
+    assert_eq!(REPORTED.load(Ordering::SeqCst), 1);
+}
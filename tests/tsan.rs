@@ -0,0 +1,66 @@
+//! Fills run under ThreadSanitizer to check the parallel write paths for data races.
+//!
+//! These run as ordinary tests too, but they're only meaningful under TSan, which needs nightly and a rebuilt std:
+//!
+//! ```text
+//! RUSTFLAGS="-Zsanitizer=thread" TSAN_OPTIONS="halt_on_error=1" \
+//!     cargo +nightly test -Zbuild-std --target x86_64-unknown-linux-gnu --test tsan
+//! ```
+use par_array_init::{from_par_iter, par_array_init, par_vec_init, try_par_array_init};
+use rayon::prelude::*;
+
+const LEN: usize = 1 << 12;
+
+fn pool() -> rayon::ThreadPool {
+    // A fixed number of threads so neighbouring tasks run concurrently even on small machines
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(8)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn par_array_init_has_no_data_races() {
+    pool().install(|| {
+        let array: Box<[u64; LEN]> = par_array_init(|i| i as u64);
+        assert!(array.iter().enumerate().all(|(i, elem)| *elem == i as u64));
+    });
+}
+
+#[test]
+fn small_elements_have_no_data_races() {
+    pool().install(|| {
+        let array: Box<[u8; LEN]> = par_array_init(|i| i as u8);
+        assert!(array.iter().enumerate().all(|(i, elem)| *elem == i as u8));
+    });
+}
+
+#[test]
+fn from_par_iter_has_no_data_races() {
+    pool().install(|| {
+        let array: Option<Box<[String; LEN]>> =
+            from_par_iter((0..LEN).into_par_iter().map(|i| i.to_string())).map(Box::new);
+        let array = array.unwrap();
+        assert!(array
+            .iter()
+            .enumerate()
+            .all(|(i, elem)| *elem == i.to_string()));
+    });
+}
+
+#[test]
+fn failed_fills_have_no_data_races() {
+    pool().install(|| {
+        let array: Result<[String; 256], usize> =
+            try_par_array_init(|i| if i == 200 { Err(i) } else { Ok(i.to_string()) });
+        assert_eq!(array, Err(200));
+    });
+}
+
+#[test]
+fn par_vec_init_has_no_data_races() {
+    pool().install(|| {
+        let vec = par_vec_init(LEN, |i| vec![i; 3]);
+        assert!(vec.iter().enumerate().all(|(i, elem)| *elem == [i; 3]));
+    });
+}
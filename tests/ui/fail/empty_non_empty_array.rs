@@ -0,0 +1,4 @@
+fn main() {
+    let array: [u32; 0] = par_array_init::par_array_init_non_empty(|i| i as u32);
+    println!("{}", array.len());
+}
@@ -0,0 +1,4 @@
+fn main() {
+    let array: [u64; 1 << 20] = par_array_init::par_stack_array_init(|i| i as u64);
+    println!("{}", array[0]);
+}
@@ -0,0 +1,6 @@
+#![deny(unused_must_use)]
+
+fn main() {
+    par_array_init::par_array_init::<[u32; 4], _>(|i| i as u32);
+    par_array_init::try_par_array_init::<[u32; 4], _, ()>(|i| Ok(i as u32));
+}
@@ -0,0 +1,4 @@
+fn main() {
+    let array: [u32; 1] = par_array_init::par_array_init_non_empty(|i| i as u32);
+    assert_eq!(array, [0]);
+}